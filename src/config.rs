@@ -0,0 +1,109 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::output::OutputFormat;
+use crate::Result;
+
+/*
+    The settings that can live in a config file. Every field is an Option so we can tell
+    "not set here" apart from a value, which is what lets the command line override the file
+    and the file override the built-in defaults one field at a time.
+    #[serde(deny_unknown_fields)] turns a typo like `dns = ...` into an error naming the key
+    instead of a setting that is silently ignored.
+*/
+#[derive(Debug, Default, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub dsn: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub connection_string: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub table: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub output: Option<OutputFormat>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub output_file: Option<PathBuf>,
+}
+
+// ~/.config/read_ecs_table/config.toml on Linux, %APPDATA%\read_ecs_table\config.toml on Windows.
+pub fn default_path() -> Option<PathBuf> {
+    dirs::config_dir().map(|dir| dir.join("read_ecs_table").join("config.toml"))
+}
+
+/*
+    Reads the config file given with --config, or the default one if it exists.
+    A missing default file just means "no config"; a missing --config file is an error,
+    since the user asked for it explicitly.
+*/
+pub fn load(explicit: Option<&Path>) -> Result<Config> {
+    let path = match explicit.map(Path::to_path_buf).or_else(default_path) {
+        Some(path) => path,
+        None => return Ok(Config::default()),
+    };
+
+    let text = match fs::read_to_string(&path) {
+        Ok(text) => text,
+        Err(e) if e.kind() == io::ErrorKind::NotFound && explicit.is_none() => {
+            return Ok(Config::default())
+        }
+        Err(e) => return Err(format!("cannot read config file '{}': {}", path.display(), e).into()),
+    };
+
+    // toml's error message already includes the line, column and offending key.
+    let config: Config = toml::from_str(&text)
+        .map_err(|e| format!("invalid config file '{}': {}", path.display(), e))?;
+
+    if config.dsn.is_some() && config.connection_string.is_some() {
+        return Err(format!(
+            "invalid config file '{}': set either dsn or connection_string, not both",
+            path.display()
+        )
+        .into());
+    }
+    Ok(config)
+}
+
+/*
+    Replaces the value of any PWD or Password key in an ODBC connection string, so the
+    effective configuration can be printed without leaking credentials. Values wrapped in
+    {braces} may themselves contain ';', so we only split on semicolons outside braces.
+*/
+pub fn redact_connection_string(conn_str: &str) -> String {
+    let mut pairs = Vec::new();
+    let mut current = String::new();
+    let mut in_braces = false;
+    let mut chars = conn_str.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '{' if !in_braces => in_braces = true,
+            // Inside braces a literal '}' is written as '}}'.
+            '}' if in_braces && chars.peek() == Some(&'}') => {
+                current.push(c);
+                chars.next();
+            }
+            '}' => in_braces = false,
+            ';' if !in_braces => {
+                pairs.push(std::mem::take(&mut current));
+                continue;
+            }
+            _ => {}
+        }
+        current.push(c);
+    }
+    pairs.push(current);
+
+    pairs
+        .iter()
+        .map(|pair| match pair.split_once('=') {
+            Some((key, _)) if ["pwd", "password"].contains(&key.trim().to_lowercase().as_str()) => {
+                format!("{}=********", key)
+            }
+            _ => pair.clone(),
+        })
+        .collect::<Vec<_>>()
+        .join(";")
+}
@@ -0,0 +1,151 @@
+use std::error::Error;
+use std::fmt;
+
+use odbc::DiagnosticRecord;
+
+use crate::RawRow;
+
+/*
+    Everything the library can fail with. Each variant says which step failed, and the row
+    mapping variants carry the column and, once it has been read, the event number, so a
+    message points at the value to go and look at.
+    Display only describes this level; the underlying cause (the driver's diagnostic, the
+    parse error) is returned by `source()`. Use `error_chain` to print the whole story.
+*/
+#[derive(Debug)]
+pub enum ReadEcsError {
+    /*
+        A DiagnosticRecord holds its message in a fixed 1 KB buffer, so it is boxed to keep
+        every Result that could carry one small.
+    */
+    // The ODBC driver manager couldn't set up an environment; usually unixODBC is missing.
+    Environment(Option<Box<DiagnosticRecord>>),
+    Connection(Box<DiagnosticRecord>),
+    // Preparing, binding, executing or fetching from a statement.
+    Query(Box<DiagnosticRecord>),
+    // A column that must have a value was NULL.
+    MissingColumn {
+        column: &'static str,
+        eventnumber: Option<i32>,
+    },
+    // The driver couldn't hand a column over as its Rust type, e.g. a value out of range.
+    Column {
+        column: &'static str,
+        eventnumber: Option<i32>,
+        source: Box<DiagnosticRecord>,
+    },
+    // A value was read but isn't valid for its column. The cause is Send + Sync, like every
+    // other field, so the error can cross threads or go into anyhow.
+    Parse {
+        column: &'static str,
+        value: String,
+        eventnumber: Option<i32>,
+        source: Box<dyn Error + Send + Sync>,
+    },
+    // A table or column name that can't be used as a SQL Server identifier.
+    Identifier(String),
+    // A fetched row that couldn't be turned into an Event; reading can carry on after it.
+    Row {
+        row: u64,
+        raw: RawRow,
+        source: Box<ReadEcsError>,
+    },
+}
+
+impl fmt::Display for ReadEcsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ReadEcsError::Environment(_) => write!(f, "could not allocate an ODBC environment"),
+            ReadEcsError::Connection(_) => write!(f, "could not connect"),
+            ReadEcsError::Query(_) => write!(f, "query failed"),
+            ReadEcsError::MissingColumn {
+                column,
+                eventnumber: Some(n),
+            } => write!(f, "event {}: missing value for '{}'", n, column),
+            // eventnumber is the key, so it's only ever NULL when the query reads something else.
+            ReadEcsError::MissingColumn {
+                column: "eventnumber",
+                eventnumber: None,
+            } => write!(
+                f,
+                "missing value for 'eventnumber' (NULL or empty); check that --table or --query reads GECSEVENTS"
+            ),
+            ReadEcsError::MissingColumn { column, eventnumber: None } => {
+                write!(f, "missing value for '{}'", column)
+            }
+            ReadEcsError::Column {
+                column,
+                eventnumber: Some(n),
+                ..
+            } => write!(f, "event {}: cannot read column '{}'", n, column),
+            ReadEcsError::Column { column, eventnumber: None, .. } => {
+                write!(f, "cannot read column '{}'", column)
+            }
+            ReadEcsError::Parse {
+                column,
+                value,
+                eventnumber: Some(n),
+                ..
+            } => write!(f, "event {}: cannot parse '{}' in column '{}'", n, value, column),
+            ReadEcsError::Parse {
+                column,
+                value,
+                eventnumber: None,
+                ..
+            } => write!(f, "cannot parse '{}' in column '{}'", value, column),
+            ReadEcsError::Identifier(message) => f.write_str(message),
+            ReadEcsError::Row { row, .. } => write!(f, "row {}", row),
+        }
+    }
+}
+
+impl Error for ReadEcsError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ReadEcsError::Environment(record) => record.as_deref().map(|r| r as &(dyn Error + 'static)),
+            ReadEcsError::Connection(record) | ReadEcsError::Query(record) => Some(record.as_ref()),
+            ReadEcsError::Column { source, .. } => Some(source.as_ref()),
+            ReadEcsError::Parse { source, .. } => Some(source.as_ref()),
+            ReadEcsError::Row { source, .. } => Some(source.as_ref()),
+            ReadEcsError::MissingColumn { .. } | ReadEcsError::Identifier(_) => None,
+        }
+    }
+}
+
+impl ReadEcsError {
+    // The event the error is about, once its eventnumber had been read; a Row error asks its cause.
+    pub fn eventnumber(&self) -> Option<i32> {
+        match self {
+            ReadEcsError::MissingColumn { eventnumber, .. }
+            | ReadEcsError::Column { eventnumber, .. }
+            | ReadEcsError::Parse { eventnumber, .. } => *eventnumber,
+            ReadEcsError::Row { source, .. } => source.eventnumber(),
+            _ => None,
+        }
+    }
+}
+
+/*
+    Almost every ODBC call is on a statement, so that is what `?` on a bare DiagnosticRecord
+    means; `connect` maps its own failures to Connection.
+*/
+impl From<DiagnosticRecord> for ReadEcsError {
+    fn from(record: DiagnosticRecord) -> Self {
+        ReadEcsError::Query(Box::new(record))
+    }
+}
+
+/*
+    Joins an error and all of its sources into one line, outermost first:
+    "row 3: event 1234567: cannot parse 'x' in column 'status': invalid digit found in string"
+*/
+pub fn error_chain(error: &dyn Error) -> String {
+    let mut message = error.to_string();
+    let mut source = error.source();
+    while let Some(cause) = source {
+        message.push_str(": ");
+        message.push_str(&cause.to_string());
+        source = cause.source();
+    }
+    message
+}
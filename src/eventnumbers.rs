@@ -0,0 +1,93 @@
+use std::fs;
+use std::path::Path;
+
+use crate::Result;
+
+/*
+    Reads --eventnumbers-file: one eventnumber per line. Blank lines and anything after a '#'
+    are ignored, so a ticket's list can be pasted in with its notes. Every line that isn't an
+    eventnumber is reported, with its line number, before anything is queried.
+    The ids come back sorted and without duplicates, which is the order the results are
+    written in.
+*/
+pub fn read(path: &Path) -> Result<Vec<i32>> {
+    let text = fs::read_to_string(path)
+        .map_err(|e| format!("cannot read '{}': {}", path.display(), e))?;
+
+    let mut ids = Vec::new();
+    let mut bad = Vec::new();
+    for (i, line) in text.lines().enumerate() {
+        // split() always yields at least one piece, the text before any '#'.
+        let line = line.split('#').next().unwrap_or_default().trim();
+        if line.is_empty() {
+            continue;
+        }
+        match line.parse::<i32>() {
+            Ok(id) => ids.push(id),
+            Err(_) => bad.push(format!("  line {}: '{}'", i + 1, line)),
+        }
+    }
+
+    if !bad.is_empty() {
+        return Err(format!(
+            "'{}' has {} line(s) that aren't eventnumbers:\n{}",
+            path.display(),
+            bad.len(),
+            bad.join("\n")
+        )
+        .into());
+    }
+    if ids.is_empty() {
+        return Err(format!("'{}' doesn't list any eventnumbers", path.display()).into());
+    }
+    ids.sort_unstable();
+    ids.dedup();
+    Ok(ids)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn fixture(name: &str) -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("tests/fixtures/eventnumbers")
+            .join(name)
+    }
+
+    #[test]
+    fn skips_comments_and_blank_lines_then_sorts_and_dedups() {
+        assert_eq!(read(&fixture("ticket.txt")).unwrap(), [-5, 1234567, 1234568, 1234570]);
+    }
+
+    #[test]
+    fn reports_every_bad_line_with_its_number() {
+        let message = read(&fixture("bad.txt")).unwrap_err().to_string();
+        assert!(message.contains("3 line(s)"), "{}", message);
+        assert!(message.contains("line 2: '12345x7'"), "{}", message);
+        assert!(message.contains("line 4: '1,234,568'"), "{}", message);
+        // Doesn't fit in an int, like the column.
+        assert!(message.contains("line 6: '99999999999'"), "{}", message);
+        assert!(!message.contains("line 1:"), "{}", message);
+    }
+
+    #[test]
+    fn an_empty_file_is_an_error() {
+        let message = read(&fixture("empty.txt")).unwrap_err().to_string();
+        assert!(message.contains("doesn't list any eventnumbers"), "{}", message);
+    }
+
+    #[test]
+    fn a_file_of_only_comments_is_an_error() {
+        let message = read(&fixture("comments_only.txt")).unwrap_err().to_string();
+        assert!(message.contains("doesn't list any eventnumbers"), "{}", message);
+    }
+
+    #[test]
+    fn a_missing_file_names_the_path() {
+        let message = read(&fixture("missing.txt")).unwrap_err().to_string();
+        assert!(message.contains("cannot read"), "{}", message);
+        assert!(message.contains("missing.txt"), "{}", message);
+    }
+}
@@ -0,0 +1,981 @@
+extern crate odbc;
+
+mod error;
+pub mod sql;
+
+pub use error::{error_chain, ReadEcsError};
+
+use chrono::{NaiveDate, NaiveDateTime};
+use odbc::*;
+use sql::Param;
+use serde::Serialize;
+use std::error::Error;
+use std::str::FromStr;
+
+/*
+    Every fallible function in the library returns this Result, so callers can match on what
+    went wrong (a bad row, a failed query, a connection that never opened) instead of only
+    being able to print it.
+*/
+pub type Result<T> = std::result::Result<T, ReadEcsError>;
+
+/*
+    #[derive(Serialize)] generates the code serde_json needs to turn an Event into a JSON object.
+    Field names are used as keys unless a #[serde(rename)] says otherwise.
+*/
+#[derive(Debug, Clone, Serialize)]
+pub struct Event {
+    /*
+        Option<T> is an enum with two variants, Some(T) and None. 
+        It's a way of expressing that a value might be absent without resorting to null or special values. 
+    */
+    pub eventnumber: i32, // MSSQL Type: PK, int, not null
+    #[serde(rename = "type")]
+    pub event_type: Option<u8>,  // MSSQL Type: tinyint, null - Using `event_type` instead of `type` because `type` is a keyword in Rust
+    pub server: Option<String>, // MSSQL Type: varchar(64), null
+    pub batch: Option<String>, // MSSQL Type: varchar(50), null
+    pub jobnum: Option<String>,  // MSSQL Type: varchar(50), null
+    pub submitted: Option<NaiveDateTime>,  // MSSQL Type: datetime, null
+    pub began: NaiveDateTime,  // MSSQL Type: PK, datetime, not null
+    pub ended: Option<NaiveDateTime>,  // MSSQL Type: datetime, null
+    pub message: Option<String>,  // MSSQL Type: varchar(255), null
+    pub status: Option<u8>,  // MSSQL Type: tinyint, null
+    pub priority: Option<u8>,  // MSSQL Type: tinyint, null
+    pub fixedby: Option<String>,  // MSSQL Type: varchar(48), null
+    pub fixcomment: Option<String>,  // MSSQL Type: varchar(255), null
+    pub color: Option<u8>,  // MSSQL Type: tinyint, null
+    pub bkcolor: Option<u8>,  // MSSQL Type: tinyint, null
+    pub beingworkedon: Option<String>,  // MSSQL Type: varchar(48), null - the username of whoever has the event open
+    pub dateclosed: Option<NaiveDateTime>,  // MSSQL Type: datetime, null
+    pub added: Option<NaiveDateTime>,  // MSSQL Type: datetime, null
+}
+
+/*
+    1. `Environment::new()`: This is calling a static method named `new` on the `Environment` struct (or type). 
+        This method typically creates and returns a new instance of the `Environment` type. In the context of ODBC, the `Environment` represents 
+        the ODBC environment which is a foundational setup needed to work with ODBC in an application. 
+    2. `.map_err(...)`: `Environment::new()` returns a `Result` whose error is an `Option<DiagnosticRecord>`, because the driver manager
+        can fail before it has anywhere to put a diagnostic. `map_err` turns either case into `ReadEcsError::Environment`, boxing the record
+        when there is one, so a missing or broken ODBC installation is reported like any other error instead of panicking inside the library.
+    The Environment must outlive every Connection made from it, which the borrow checker enforces through the `'env` lifetime.
+*/
+pub fn create_environment() -> Result<Environment<Version3>> {
+    Environment::new().map_err(|e| match e {
+        Some(record) => ReadEcsError::Environment(Some(Box::new(record))),
+        None => ReadEcsError::Environment(None),
+    })
+}
+
+pub fn connect<'env>(
+    env: &'env Environment<Version3>,
+    conn_str: &str,
+) -> Result<Connection<'env, safe::AutocommitOn>> {
+    env.connect_with_connection_string(conn_str)
+        .map_err(|e| ReadEcsError::Connection(Box::new(e)))
+}
+
+/*
+    Runs `query` over a connection made from `conn_str` and returns every row as an Event,
+    stopping at the first error. The whole result set is held in memory; use EventReader to
+    stream large tables, or to hear about values that were read as NULL (see UnparsedValue).
+*/
+pub fn fetch_events(conn_str: &str, query: &str) -> Result<Vec<Event>> {
+    let env = create_environment()?;
+    let conn = connect(&env, conn_str)?;
+    let events = EventReader::new(&conn, query)?.collect();
+    events
+}
+
+/*
+    Streams the result of a query one Event at a time: each call to `next()` does one
+    `stmt.fetch()` and maps that row, so nothing is collected in memory.
+
+    The odbc crate ties its types together with lifetimes: a Connection borrows the
+    Environment it came from, and a Statement borrows its Connection ('conn here). Rust has no
+    convenient way for one struct to own a value and something borrowing it at the same time,
+    so the reader owns only the Statement and borrows the caller's Connection; the borrow
+    checker then guarantees the Connection (and so the Environment) outlives the reader.
+
+        let env = create_environment()?;
+        let conn = connect(&env, "DSN=GECS_Testing;")?;
+        for event in EventReader::new(&conn, "SELECT * FROM [dbo].[GECSEVENTS];")? {
+            println!("{}", event?.eventnumber);
+        }
+
+    Dropping the reader early closes the cursor.
+
+    A row that was fetched but couldn't be turned into an Event comes out as
+    ReadEcsError::Row, so a caller can tell a bad row, after which reading may continue, from
+    a failed fetch, after which the reader is finished:
+
+        match event {
+            Ok(event) => { /* use it */ }
+            Err(ReadEcsError::Row { row, raw, source }) => { /* log them, keep going */ }
+            Err(fatal) => return Err(fatal),
+        }
+*/
+pub struct EventReader<'conn> {
+    // None once the rows run out, after a fetch error, or if the query returned no result set.
+    stmt: Option<Statement<'conn, 'conn, Executed, HasResult, safe::AutocommitOn>>,
+    has_result_set: bool,
+    row: u64,
+    mode: FetchMode,
+    unparsed: Vec<UnparsedValue>,
+}
+
+/*
+    How EventReader asks the driver for column values.
+    Typed (the default) fetches each column as its own type: i32 for eventnumber, u8 for the
+    tinyints and SQL_TIMESTAMP_STRUCT for the datetimes, so the driver does the conversion and
+    only the text columns allocate. Strings fetches every column as text and parses it here,
+    the way the tool always used to; it's the fallback for drivers that report a column's type
+    wrongly, and the only mode in which a failed row's raw text is kept, so --error-file uses it.
+*/
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum FetchMode {
+    #[default]
+    Typed,
+    Strings,
+}
+
+impl<'conn> EventReader<'conn> {
+    pub fn new(conn: &'conn Connection<'_, safe::AutocommitOn>, query: &str) -> Result<Self> {
+        Self::with_params(conn, query, &[])
+    }
+
+    /*
+        Like `new`, but first binds `params` to the query's `?` placeholders, in order. The
+        driver reads the bound values when the statement executes and again on some fetches, so
+        the borrow of `params` has to last as long as the reader; the shared 'conn lifetime
+        makes the borrow checker hold us to that.
+    */
+    pub fn with_params(
+        conn: &'conn Connection<'_, safe::AutocommitOn>,
+        query: &str,
+        params: &'conn [Param],
+    ) -> Result<Self> {
+        /*
+            `Statement::with_parent(&conn)?` is a method call on the `Statement` type. In the context of ODBC:
+        1. `Statement`: In ODBC, a statement is an object that allows you to execute SQL commands and queries against a database. 
+            Once you have a connection to a database (represented by the `conn` variable), you can create one or more statements to interact with that database.
+        2. `with_parent(&conn)`: The `with_parent` method is used to create a new statement that is associated with a particular connection. 
+            The method takes a reference to a connection (`&conn` in this case) as its argument, indicating that the new statement will use that connection to communicate 
+            with the database.
+        3. `?`: This is the try operator in Rust. If `Statement::with_parent(&conn)` returns an `Ok` variant of a `Result`, the value inside that `Ok` is extracted. 
+            If it returns an `Err` variant (indicating an error occurred while creating the statement), then the error is returned early from the current function.
+            In essence, `let stmt = Statement::with_parent(&conn)?;` is trying to create a new ODBC statement associated with the given database connection, and if successful, 
+            binds it to the variable `stmt`. If there's an error, the current function will return early with that error.
+        4.  `mut` because `bind_parameter` consumes the statement and hands back a new one, which we store in the same variable.
+        */
+        let mut stmt = Statement::with_parent(conn)?;
+
+        // ODBC numbers parameters from 1, like columns.
+        for (i, param) in params.iter().enumerate() {
+            let index = (i + 1) as u16;
+            stmt = match param {
+                Param::Int(value) => stmt.bind_parameter(index, value)?,
+                Param::Text(value) => stmt.bind_parameter(index, value)?,
+            };
+        }
+
+        /*
+            Function Call: The method 'exec_direct' is being called on the stmt object (which is an instance of Statement). 
+            This method attempts to directly execute a given SQL command or query represented by the string query.
+            Why does exec_direct take a string 'reference' &str instead of a string 'value' str?
+            The function doesn't need to own the string; it just needs to read it.
+            Also after the call to exec_direct, query can still be used or modified in your code if needed.
+            By accepting a reference, the function can operate on the data without taking ownership, which can help prevent unnecessary allocations or data movements.
+            The Try Operator (?): After exec_direct is called, the ? operator is used. This operator checks the Result returned by exec_direct. 
+            If the Result is an Ok variant (indicating the operation was successful), it will extract the value inside the Ok for further use. 
+            If the Result is an Err variant (indicating an error occurred during the execution of the SQL statement), it will immediately return that error from the current function.
+            Pattern Matching with match: The value extracted from the Ok variant (or, in another way to think about it, the result of the successful execution of the SQL statement) 
+            is then passed into a match expression. A match expression in Rust is used for pattern matching: 
+            it allows you to check the value against several potential patterns and execute code based on which pattern the value matches.
+            In this specific case, it's likely that exec_direct returns a Result where the "successful" type can be one of two variants, 
+            probably something like Data (indicating that the SQL statement returned some data) and NoData (indicating that the SQL statement executed successfully 
+            but did not return any data, like an UPDATE or DELETE command in SQL might).
+            The code that follows the match expression will contain branches for each of these patterns, specifying what to do in each case.
+            Data() & NoData()
+        */
+
+        let stmt = match stmt.exec_direct(query)? {
+            Data(stmt) => Some(stmt),
+            NoData(_) => None,
+        };
+        Ok(EventReader {
+            has_result_set: stmt.is_some(),
+            stmt,
+            row: 0,
+            mode: FetchMode::default(),
+            unparsed: Vec::new(),
+        })
+    }
+
+    // EventReader::new(&conn, query)?.fetch_mode(FetchMode::Strings)
+    pub fn fetch_mode(mut self, mode: FetchMode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    // False when the statement ran but produced no result set, e.g. an UPDATE passed as the query.
+    pub fn has_result_set(&self) -> bool {
+        self.has_result_set
+    }
+
+    /*
+        Compares the result set's columns, as the driver describes them, with COLUMNS and
+        returns one line per disagreement: a missing or extra column, a different name, or a
+        type Event would misread (say an int where Event expects text). An empty Vec means the
+        query returned the layout Event was written for.
+    */
+    pub fn check_columns(&self) -> Result<Vec<String>> {
+        let stmt = match &self.stmt {
+            Some(stmt) => stmt,
+            None => return Ok(Vec::new()),
+        };
+        let mut problems = Vec::new();
+        let count = stmt.num_result_cols()?;
+        if count != COLUMN_COUNT as i16 {
+            problems.push(format!("the query returns {} columns; Event expects {}", count, COLUMN_COUNT));
+        }
+        for (i, expected) in COLUMNS.iter().enumerate().take(count.max(0) as usize) {
+            let described = stmt.describe_col(i as u16 + 1)?;
+            if !described.name.eq_ignore_ascii_case(expected.name) {
+                problems.push(format!(
+                    "column {} is '{}'; Event expects '{}' there",
+                    i + 1,
+                    described.name,
+                    expected.name
+                ));
+            }
+            if !expected.column_type.accepts(described.data_type) {
+                problems.push(format!(
+                    "column {} '{}' is {:?}; Event reads it as {:?}",
+                    i + 1,
+                    described.name,
+                    described.data_type,
+                    expected.column_type
+                ));
+            }
+        }
+        Ok(problems)
+    }
+
+    // How many rows have been fetched so far, including any that failed to map.
+    pub fn rows_fetched(&self) -> u64 {
+        self.row
+    }
+
+    /*
+        The values read as NULL because they didn't parse, since the last call. An Event
+        returned by `next()` has its own in here straight away, so calling this after each row
+        pairs them up; the values of a row that failed altogether are dropped with it.
+    */
+    pub fn take_unparsed(&mut self) -> Vec<UnparsedValue> {
+        std::mem::take(&mut self.unparsed)
+    }
+}
+
+/*
+    Implementing Iterator is what lets callers write `for event in reader` or use adapters such
+    as `take`, `filter` and `collect`. `Item` is the type each call to `next()` produces; here
+    every row can fail on its own, so it's a Result rather than a bare Event.
+*/
+impl Iterator for EventReader<'_> {
+    type Item = Result<Event>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let stmt = self.stmt.as_mut()?;
+        match stmt.fetch() {
+            Ok(Some(mut cursor)) => {
+                self.row += 1;
+                let row = self.row;
+                let result = match self.mode {
+                    FetchMode::Typed => {
+                        read_typed_event(&mut cursor).map_err(|source| (RawRow::default(), source))
+                    }
+                    FetchMode::Strings => match RawRow::read(&mut cursor) {
+                        Ok(raw) => {
+                            let kept = self.unparsed.len();
+                            read_event(&raw, &mut self.unparsed).map_err(|source| {
+                                self.unparsed.truncate(kept);
+                                (raw, source)
+                            })
+                        }
+                        // A column that couldn't be read at all; there's nothing raw to keep.
+                        Err(source) => Err((RawRow::default(), source)),
+                    },
+                };
+                Some(result.map_err(|(raw, source)| ReadEcsError::Row {
+                    row,
+                    raw,
+                    source: Box::new(source),
+                }))
+            }
+            Ok(None) => {
+                self.close();
+                None
+            }
+            Err(e) => {
+                self.close();
+                Some(Err(e.into()))
+            }
+        }
+    }
+}
+
+impl EventReader<'_> {
+    fn close(&mut self) {
+        if let Some(stmt) = self.stmt.take() {
+            // Errors closing a cursor we are abandoning aren't actionable; the handle is freed either way.
+            let _ = stmt.close_cursor();
+        }
+    }
+}
+
+impl Drop for EventReader<'_> {
+    fn drop(&mut self) {
+        self.close();
+    }
+}
+
+/*
+    One row exactly as the driver returned it: every column fetched as text, NULL as None.
+    The text is kept until the row has been mapped, so a row that fails can still be reported,
+    or written to --error-file, value by value.
+*/
+#[derive(Debug, Clone, Default)]
+pub struct RawRow {
+    pub values: Vec<Option<String>>,
+}
+
+/*
+    A nullable datetime whose text didn't parse in FetchMode::Strings. Rather than lose the
+    whole row to one odd value, the column is read as None and the value is kept here, so the
+    caller can say so and it isn't mistaken for a real NULL. The library doesn't print it.
+*/
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnparsedValue {
+    pub column: &'static str,
+    pub value: String,
+    pub eventnumber: Option<i32>,
+}
+
+impl std::fmt::Display for UnparsedValue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if let Some(n) = self.eventnumber {
+            write!(f, "event {}: ", n)?;
+        }
+        write!(f, "cannot parse '{}' in column '{}'", self.value, self.column)
+    }
+}
+
+// Columns in a GECSEVENTS row, and so in every RawRow read by EventReader.
+pub const COLUMN_COUNT: u16 = 18;
+
+// The kinds of column check_columns tells apart, one per Rust type Event reads a column into.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColumnType {
+    Int,
+    TinyInt,
+    Text,
+    DateTime,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Column {
+    pub name: &'static str,
+    pub column_type: ColumnType,
+}
+
+const fn col(name: &'static str, column_type: ColumnType) -> Column {
+    Column { name, column_type }
+}
+
+/*
+    The layout of a GECSEVENTS row as Event expects it, in result set order, matching the MSSQL
+    types noted on Event's fields. The names are also the CSV header. The JSON keys come from
+    Event's field names and serde attributes instead; they are the same names, and a test keeps
+    them that way.
+*/
+pub const COLUMNS: [Column; COLUMN_COUNT as usize] = [
+    col("eventnumber", ColumnType::Int),
+    col("type", ColumnType::TinyInt),
+    col("server", ColumnType::Text),
+    col("batch", ColumnType::Text),
+    col("jobnum", ColumnType::Text),
+    col("submitted", ColumnType::DateTime),
+    col("began", ColumnType::DateTime),
+    col("ended", ColumnType::DateTime),
+    col("message", ColumnType::Text),
+    col("status", ColumnType::TinyInt),
+    col("priority", ColumnType::TinyInt),
+    col("fixedby", ColumnType::Text),
+    col("fixcomment", ColumnType::Text),
+    col("color", ColumnType::TinyInt),
+    col("bkcolor", ColumnType::TinyInt),
+    col("beingworkedon", ColumnType::Text),
+    col("dateclosed", ColumnType::DateTime),
+    col("added", ColumnType::DateTime),
+];
+
+impl ColumnType {
+    // Whether a driver-reported SQL type can hold this column. Drivers differ in which of the
+    // equivalent codes they use for text and datetime columns, so each kind accepts a few.
+    fn accepts(self, data_type: ffi::SqlDataType) -> bool {
+        use ffi::SqlDataType::*;
+        match self {
+            ColumnType::Int => data_type == SQL_INTEGER,
+            ColumnType::TinyInt => data_type == SQL_EXT_TINYINT,
+            ColumnType::Text => matches!(
+                data_type,
+                SQL_CHAR | SQL_VARCHAR | SQL_EXT_LONGVARCHAR | SQL_EXT_WCHAR | SQL_EXT_WVARCHAR | SQL_EXT_WLONGVARCHAR
+            ),
+            ColumnType::DateTime => matches!(data_type, SQL_TIMESTAMP | SQL_DATETIME | SQL_EXT_TIMESTAMP),
+        }
+    }
+}
+
+impl RawRow {
+    fn read<S, AC: safe::AutocommitMode>(cursor: &mut Cursor<S, AC>) -> Result<RawRow> {
+        /*
+            1. **`cursor.get_data(column)?`**: 
+                - The `cursor` object represents a position within a result set from a database query.
+                - The `get_data` method is being called on the `cursor` to retrieve the data from one column of the current row, counting from 1.
+                - The type of data that `get_data` returns is generic and can vary. In this context, it's expected to be an `Option<T>` where `T` is the type like `String`.
+                - The `?` operator is used for error propagation in Rust. If `get_data` returns an error, the function will immediately return that error. 
+                  If `get_data` succeeds, it will give back the contained value from the `Ok` variant.
+         */
+        let mut values = Vec::with_capacity(COLUMN_COUNT as usize);
+        for column in 1..=COLUMN_COUNT {
+            values.push(cursor.get_data::<String>(column)?);
+        }
+        Ok(RawRow { values })
+    }
+
+    // Column `n`, counting from 1 like get_data. Missing columns read as NULL.
+    fn get(&self, n: usize) -> Option<String> {
+        self.values.get(n - 1).cloned().flatten()
+    }
+}
+
+/*
+    Maps a raw row onto an Event. A value that doesn't parse, or a NULL where one isn't
+    allowed, is returned to EventReader::next, which wraps it in ReadEcsError::Row for that row;
+    a nullable datetime that doesn't parse is read as None and added to `unparsed` instead.
+    Once the eventnumber has been read it goes into every error, so a message names the event
+    as well as the column.
+*/
+fn read_event(raw: &RawRow, unparsed: &mut Vec<UnparsedValue>) -> Result<Event> {
+    // Some drivers hand a NULL key in a view back as an empty string; treat both the same.
+    let eventnumber_str: Option<String> = raw.get(1).filter(|s| !s.trim().is_empty());
+    let eventnumber: i32 = parse_column("eventnumber", eventnumber_str, None)?.ok_or(
+        ReadEcsError::MissingColumn {
+            column: "eventnumber",
+            eventnumber: None,
+        },
+    )?;
+    let known = Some(eventnumber);
+
+    let eventtype_str: Option<String> = raw.get(2);
+    /*
+        `parse_column` turns the Option<String> from the cursor into an Option<u8>:
+        - NULL (None) stays None; that's a legal value for every tinyint column.
+        - Some("3") becomes Some(3).
+        - Some("abc") or Some("300") is an error naming the column, the value and the event,
+          rather than quietly becoming None and looking like a NULL in the output.
+        The `?` then returns that error from read_event, and `known` is the eventnumber read above.
+    */
+    let event_type = parse_column::<u8>("type", eventtype_str, known)?;
+
+    let server: Option<String> = raw.get(3);
+
+    let batch: Option<String> = raw.get(4);
+
+    let jobnum: Option<String> = raw.get(5);
+
+    let submitted_str: Option<String> = raw.get(6);
+    let submitted = optional_datetime("submitted", submitted_str, known, unparsed);
+
+    let began_str: String = raw.get(7).ok_or(ReadEcsError::MissingColumn {
+        column: "began",
+        eventnumber: known,
+    })?;
+    let began = parse_datetime(&began_str).ok_or_else(|| ReadEcsError::Parse {
+        column: "began",
+        value: began_str.clone(),
+        eventnumber: known,
+        source: "not a date and time in any format we recognise".into(),
+    })?;
+
+    let ended_str: Option<String> = raw.get(8);
+    let ended = optional_datetime("ended", ended_str, known, unparsed);
+
+    let message: Option<String> = raw.get(9);
+
+    let status_str: Option<String> = raw.get(10);
+    let status = parse_column::<u8>("status", status_str, known)?;
+
+    let priority_str: Option<String> = raw.get(11);
+    let priority = parse_column::<u8>("priority", priority_str, known)?;
+
+    let fixedby: Option<String> = raw.get(12);
+
+    let fixcomment: Option<String> = raw.get(13);
+
+    let color_str: Option<String> = raw.get(14);
+    let color = parse_column::<u8>("color", color_str, known)?;
+
+    let bkcolor_str: Option<String> = raw.get(15);
+    let bkcolor = parse_column::<u8>("bkcolor", bkcolor_str, known)?;
+
+    let beingworkedon: Option<String> = raw.get(16);
+
+    let dateclosed_str: Option<String> = raw.get(17);
+    let dateclosed = optional_datetime("dateclosed", dateclosed_str, known, unparsed);
+
+    let added_str: Option<String> = raw.get(18);
+    let added = optional_datetime("added", added_str, known, unparsed);
+
+    Ok(Event {
+        eventnumber,
+        event_type,
+        server,
+        batch,
+        jobnum,
+        message,
+        status,
+        priority,
+        fixedby,
+        fixcomment,
+        color,
+        bkcolor,
+        beingworkedon,
+        submitted,
+        began,
+        ended,
+        dateclosed,
+        added,
+        // submitted: cursor.get_data(6)?,
+        // began: cursor.get_data(7)?,
+        // ended: cursor.get_data(8)?,
+        // dateclosed: cursor.get_data(17)?,
+        // added: cursor.get_data(18)?.unwrap_or_default(),
+    })
+}
+
+/*
+    The FetchMode::Typed counterpart of read_event: reads each column straight into its Rust
+    type. get_data is generic over the type it returns, so the annotation on each `let` (or the
+    turbofish, `get_data::<u8>`) is what tells the driver which C type to convert the value to.
+    `for<'d> Output<'d>` says T must be readable for any borrow of the cursor, which is true of
+    every owned type such as i32 or String.
+*/
+fn read_typed_event<S, AC: safe::AutocommitMode>(cursor: &mut Cursor<S, AC>) -> Result<Event> {
+    fn column<T, S, AC: safe::AutocommitMode>(
+        cursor: &mut Cursor<S, AC>,
+        n: u16,
+        column: &'static str,
+        eventnumber: Option<i32>,
+    ) -> Result<Option<T>>
+    where
+        T: for<'d> Output<'d>,
+    {
+        cursor.get_data::<T>(n).map_err(|e| ReadEcsError::Column {
+            column,
+            eventnumber,
+            source: Box::new(e),
+        })
+    }
+
+    let eventnumber: i32 = column(cursor, 1, "eventnumber", None)?.ok_or(
+        ReadEcsError::MissingColumn {
+            column: "eventnumber",
+            eventnumber: None,
+        },
+    )?;
+    let known = Some(eventnumber);
+
+    let datetime = |cursor: &mut Cursor<S, AC>, n, name| -> Result<Option<NaiveDateTime>> {
+        column::<SqlTimestamp, S, AC>(cursor, n, name, known)?
+            .map(|ts| timestamp_to_datetime(&ts, name, known))
+            .transpose()
+    };
+
+    let event_type = column(cursor, 2, "type", known)?;
+    let server = column(cursor, 3, "server", known)?;
+    let batch = column(cursor, 4, "batch", known)?;
+    let jobnum = column(cursor, 5, "jobnum", known)?;
+    let submitted = datetime(cursor, 6, "submitted")?;
+    let began = datetime(cursor, 7, "began")?.ok_or(ReadEcsError::MissingColumn {
+        column: "began",
+        eventnumber: known,
+    })?;
+    let ended = datetime(cursor, 8, "ended")?;
+    let message = column(cursor, 9, "message", known)?;
+    let status = column(cursor, 10, "status", known)?;
+    let priority = column(cursor, 11, "priority", known)?;
+    let fixedby = column(cursor, 12, "fixedby", known)?;
+    let fixcomment = column(cursor, 13, "fixcomment", known)?;
+    let color = column(cursor, 14, "color", known)?;
+    let bkcolor = column(cursor, 15, "bkcolor", known)?;
+    let beingworkedon = column(cursor, 16, "beingworkedon", known)?;
+    let dateclosed = datetime(cursor, 17, "dateclosed")?;
+    let added = datetime(cursor, 18, "added")?;
+
+    Ok(Event {
+        eventnumber,
+        event_type,
+        server,
+        batch,
+        jobnum,
+        submitted,
+        began,
+        ended,
+        message,
+        status,
+        priority,
+        fixedby,
+        fixcomment,
+        color,
+        bkcolor,
+        beingworkedon,
+        dateclosed,
+        added,
+    })
+}
+
+/*
+    SQL_TIMESTAMP_STRUCT holds the date and time as separate fields, with `fraction` in
+    nanoseconds. chrono's *_opt constructors return None for an impossible date such as
+    February 30th, which we report like any other bad value.
+*/
+fn timestamp_to_datetime(ts: &SqlTimestamp, column: &'static str, eventnumber: Option<i32>) -> Result<NaiveDateTime> {
+    NaiveDate::from_ymd_opt(ts.year.into(), ts.month.into(), ts.day.into())
+        .and_then(|date| {
+            date.and_hms_nano_opt(ts.hour.into(), ts.minute.into(), ts.second.into(), ts.fraction)
+        })
+        .ok_or_else(|| ReadEcsError::Parse {
+            column,
+            value: format!(
+                "{:04}-{:02}-{:02} {:02}:{:02}:{:02}.{:09}",
+                ts.year, ts.month, ts.day, ts.hour, ts.minute, ts.second, ts.fraction
+            ),
+            eventnumber,
+            source: "not a valid date and time".into(),
+        })
+}
+
+/*
+    The text forms SQL Server ODBC drivers have been seen to return for date and time columns,
+    tried in this order. `%.f` matches an optional fraction of any length, so
+    "2023-10-01 13:45:00", "2023-10-01 13:45:00.000" and "2023-10-01 13:45:00.1234567" all
+    parse with the first entry. The 'T' forms come from ISO 8601 output, the offset forms from
+    datetimeoffset columns (the offset is dropped, as every other column is zone-less local
+    time), and a bare date from a date column, which is taken as midnight.
+*/
+const DATETIME_FORMATS: [&str; 4] = [
+    "%Y-%m-%d %H:%M:%S%.f",
+    "%Y-%m-%dT%H:%M:%S%.f",
+    "%Y-%m-%d %H:%M:%S%.f %:z",
+    "%Y-%m-%dT%H:%M:%S%.f%:z",
+];
+
+pub fn parse_datetime(text: &str) -> Option<NaiveDateTime> {
+    let text = text.trim();
+    DATETIME_FORMATS
+        .iter()
+        .find_map(|format| NaiveDateTime::parse_from_str(text, format).ok())
+        .or_else(|| {
+            NaiveDate::parse_from_str(text, "%Y-%m-%d")
+                .ok()
+                .and_then(|date| date.and_hms_opt(0, 0, 0))
+        })
+}
+
+/*
+    For the nullable datetime columns. NULL stays None quietly; text that doesn't parse also
+    becomes None, so one odd value doesn't cost the whole row, and is recorded in `unparsed`
+    with the raw string.
+*/
+fn optional_datetime(
+    column: &'static str,
+    value: Option<String>,
+    eventnumber: Option<i32>,
+    unparsed: &mut Vec<UnparsedValue>,
+) -> Option<NaiveDateTime> {
+    let value = value?;
+    let parsed = parse_datetime(&value);
+    if parsed.is_none() {
+        unparsed.push(UnparsedValue {
+            column,
+            value,
+            eventnumber,
+        });
+    }
+    parsed
+}
+
+/*
+    Parses one column's text into T, keeping NULL as None. Any FromStr type works (u8, i32, ...);
+    a value that doesn't parse becomes ReadEcsError::Parse with the column, the raw value and,
+    when it is known, the event number.
+*/
+fn parse_column<T>(column: &'static str, value: Option<String>, eventnumber: Option<i32>) -> Result<Option<T>>
+where
+    T: FromStr,
+    T::Err: Error + Send + Sync + 'static,
+{
+    let value = match value {
+        Some(value) => value,
+        None => return Ok(None),
+    };
+    // char columns come back padded with spaces, which FromStr won't skip.
+    match value.trim().parse() {
+        Ok(parsed) => Ok(Some(parsed)),
+        Err(e) => Err(ReadEcsError::Parse {
+            column,
+            value,
+            eventnumber,
+            source: Box::new(e),
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A row as FetchMode::Strings reads it, with the two NOT NULL columns (eventnumber and
+    // began) filled in. Each (n, value) pair then overwrites column n, counting from 1.
+    fn raw_row(columns: &[(usize, Option<&str>)]) -> RawRow {
+        let mut values = vec![None; COLUMN_COUNT as usize];
+        values[0] = Some("1234567".to_string());
+        values[6] = Some("2023-10-01 13:45:00.000".to_string());
+        for &(n, value) in columns {
+            values[n - 1] = value.map(str::to_string);
+        }
+        RawRow { values }
+    }
+
+    fn read_event_quietly(raw: &RawRow) -> Result<Event> {
+        read_event(raw, &mut Vec::new())
+    }
+
+    #[test]
+    fn beingworkedon_keeps_the_username() {
+        let event = read_event_quietly(&raw_row(&[(16, Some("jsmith"))])).unwrap();
+        assert_eq!(event.beingworkedon, Some("jsmith".into()));
+    }
+
+    #[test]
+    fn beingworkedon_null_stays_none() {
+        let event = read_event_quietly(&raw_row(&[])).unwrap();
+        assert_eq!(event.beingworkedon, None);
+    }
+
+    fn assert_eventnumber_missing(value: Option<&str>) {
+        match read_event_quietly(&raw_row(&[(1, value)])) {
+            Err(ReadEcsError::MissingColumn {
+                column: "eventnumber",
+                eventnumber: None,
+            }) => {}
+            other => panic!("{:?}: expected a missing eventnumber, got {:?}", value, other),
+        }
+    }
+
+    #[test]
+    fn eventnumber_null_is_missing() {
+        assert_eventnumber_missing(None);
+    }
+
+    #[test]
+    fn eventnumber_empty_is_missing() {
+        assert_eventnumber_missing(Some(""));
+    }
+
+    #[test]
+    fn eventnumber_blank_is_missing() {
+        assert_eventnumber_missing(Some("   "));
+    }
+
+    #[test]
+    fn eventnumber_garbage_is_a_parse_error() {
+        match read_event_quietly(&raw_row(&[(1, Some("abc"))])) {
+            Err(ReadEcsError::Parse {
+                column: "eventnumber",
+                value,
+                eventnumber: None,
+                ..
+            }) => assert_eq!(value, "abc"),
+            other => panic!("expected a parse error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn json_keys_match_columns() {
+        let json = serde_json::to_value(read_event_quietly(&raw_row(&[])).unwrap()).unwrap();
+        let mut keys: Vec<&str> = json.as_object().unwrap().keys().map(String::as_str).collect();
+        let mut names: Vec<&str> = COLUMNS.iter().map(|column| column.name).collect();
+        keys.sort_unstable();
+        names.sort_unstable();
+        assert_eq!(keys, names);
+    }
+
+    #[test]
+    fn row_errors_know_their_eventnumber() {
+        let source = read_event_quietly(&raw_row(&[(10, Some("300"))])).unwrap_err();
+        let row = ReadEcsError::Row {
+            row: 3,
+            raw: RawRow::default(),
+            source: Box::new(source),
+        };
+        assert_eq!(row.eventnumber(), Some(1234567));
+
+        let unknown = read_event_quietly(&raw_row(&[(1, Some("abc"))])).unwrap_err();
+        assert_eq!(unknown.eventnumber(), None);
+    }
+
+    #[test]
+    fn errors_can_cross_threads() {
+        fn send_sync<T: Send + Sync + 'static>() {}
+        send_sync::<ReadEcsError>();
+    }
+
+    #[test]
+    fn missing_eventnumber_points_at_table_and_query() {
+        let error = ReadEcsError::MissingColumn {
+            column: "eventnumber",
+            eventnumber: None,
+        };
+        let message = error.to_string();
+        assert!(message.contains("--table"), "{}", message);
+        assert!(message.contains("--query"), "{}", message);
+    }
+
+    #[test]
+    fn unparsed_datetime_is_none_and_recorded() {
+        let mut unparsed = Vec::new();
+        let event = read_event(&raw_row(&[(8, Some("not a date"))]), &mut unparsed).unwrap();
+        assert_eq!(event.ended, None);
+        assert_eq!(
+            unparsed,
+            vec![UnparsedValue {
+                column: "ended",
+                value: "not a date".to_string(),
+                eventnumber: Some(1234567),
+            }]
+        );
+        assert_eq!(
+            unparsed[0].to_string(),
+            "event 1234567: cannot parse 'not a date' in column 'ended'"
+        );
+    }
+
+    #[test]
+    fn null_datetime_is_not_recorded() {
+        let mut unparsed = Vec::new();
+        read_event(&raw_row(&[(8, None)]), &mut unparsed).unwrap();
+        assert!(unparsed.is_empty());
+    }
+
+    fn datetime(text: &str) -> NaiveDateTime {
+        NaiveDateTime::parse_from_str(text, "%Y-%m-%d %H:%M:%S%.f").unwrap()
+    }
+
+    #[test]
+    fn parse_datetime_without_fraction() {
+        assert_eq!(parse_datetime("2023-10-01 13:45:00"), Some(datetime("2023-10-01 13:45:00")));
+    }
+
+    #[test]
+    fn parse_datetime_with_milliseconds() {
+        assert_eq!(parse_datetime("2023-10-01 13:45:00.000"), Some(datetime("2023-10-01 13:45:00")));
+        assert_eq!(parse_datetime("2023-10-01 13:45:00.003"), Some(datetime("2023-10-01 13:45:00.003")));
+    }
+
+    // datetime2(7), as the newer drivers return it.
+    #[test]
+    fn parse_datetime_with_seven_digit_fraction() {
+        assert_eq!(
+            parse_datetime("2023-10-01 13:45:00.1234567"),
+            Some(datetime("2023-10-01 13:45:00.1234567"))
+        );
+    }
+
+    #[test]
+    fn parse_datetime_with_t_separator() {
+        assert_eq!(parse_datetime("2023-10-01T13:45:00.003"), Some(datetime("2023-10-01 13:45:00.003")));
+    }
+
+    // The offset is dropped, not applied: every other GECS time is local already.
+    #[test]
+    fn parse_datetime_with_offset() {
+        assert_eq!(
+            parse_datetime("2023-10-01 13:45:00.0000000 +02:00"),
+            Some(datetime("2023-10-01 13:45:00"))
+        );
+        assert_eq!(parse_datetime("2023-10-01T13:45:00-05:00"), Some(datetime("2023-10-01 13:45:00")));
+    }
+
+    #[test]
+    fn parse_datetime_bare_date_is_midnight() {
+        assert_eq!(parse_datetime("2023-10-01"), Some(datetime("2023-10-01 00:00:00")));
+    }
+
+    // char columns and some drivers pad values with spaces.
+    #[test]
+    fn parse_datetime_ignores_padding() {
+        assert_eq!(parse_datetime("  2023-10-01 13:45:00.003  "), Some(datetime("2023-10-01 13:45:00.003")));
+    }
+
+    #[test]
+    fn parse_datetime_rejects_other_text() {
+        assert_eq!(parse_datetime("10/01/2023 1:45 PM"), None);
+        assert_eq!(parse_datetime("2023-02-30 00:00:00"), None);
+        assert_eq!(parse_datetime(""), None);
+    }
+}
+
+// use tiberius::{Client, Config, QueryItem};
+// use tokio::net::TcpStream;
+// use tokio_util::compat::TokioAsyncReadCompatExt;
+// use futures::stream::StreamExt;
+// extern crate odbc;
+// use odbc::*;
+
+// #[tokio::main]
+// async fn main() -> Result<(), Box<dyn std::error::Error>> {
+//     // Hardcoded connection string for testing. NEVER do this in production or in shared code.
+//     let conn_str = "server=tcp:MINI\\BEESERVER,1433;user id=sa;password=jollyroger1;initial catalog=GECS_Testing;trustServerCertificate=true;";
+
+//     let config = Config::from_ado_string(&conn_str)?;
+
+//     // Create a tokio TcpStream
+//     let tokio_stream = TcpStream::connect(config.get_addr()).await?;
+
+//     // Convert the tokio TcpStream to a compatible stream
+//     let compat_stream = tokio_stream.compat();
+
+//     // Now use the compat_stream to connect with tiberius
+//     let mut client = Client::connect(config, compat_stream).await?;
+
+//     println!("Connected!");
+
+//     let mut query_stream = client.simple_query("SELECT column_name FROM your_table").await?;
+
+//     while let Some(item_result) = query_stream.next().await {
+//         match item_result {
+//             Ok(QueryItem::Row(row)) => {
+//                 let column: &str = row.get(0).unwrap();
+//                 println!("{}", column);
+//             },
+//             Ok(_) => {},  // Handle other types of QueryItem if needed
+//             Err(e) => {
+//                 eprintln!("Error processing row: {}", e);
+//             }
+//         }
+//     }
+
+//     Ok(())
+// }
@@ -1,8 +1,22 @@
-extern crate odbc;
-
-use chrono::NaiveDateTime;
-use odbc::*;
+mod config;
+mod eventnumbers;
+mod output;
+mod show;
+mod watch;
+mod timespec;
+
+use clap::{Parser, Subcommand, ValueEnum};
+use config::Config;
+use output::OutputFormat;
+use read_gecs_tables::{
+    connect, create_environment, error_chain, sql, EventReader, FetchMode, ReadEcsError,
+};
 use std::error::Error;
+use std::collections::BTreeSet;
+use std::fs::File;
+use std::io::{self, BufWriter, IsTerminal, Write};
+use std::path::{Path, PathBuf};
+use std::process;
 
 /* 
 type Result<T> = ...: This is defining a type alias named Result that takes a generic parameter T.
@@ -11,365 +25,602 @@ which represents either a success (Ok variant) or an error (Err variant).
 Box<dyn Error>: This represents a heap-allocated trait object of something that implements the Error trait. 
 The dyn keyword indicates a trait object (a type of dynamic dispatch), and Box is a heap-allocated smart pointer.
 This is a type alias for a Result with the error type being a trait object. This allows us to return any type that implements the Error trait.
-*/ 
+The library has its own ReadEcsError; the CLI code mixes it with io, csv, serde and config errors, and `?` boxes any of them.
+*/
+type Result<T> = std::result::Result<T, Box<dyn Error>>;
+
+const DEFAULT_DSN: &str = "GECS_Testing";
+const DEFAULT_TABLE: &str = "GECS_Testing.dbo.GECSEVENTS";
+
+/*
+    #[derive(Parser)] asks clap to generate the command line parser from this struct:
+    each field becomes a flag named after it (`output` -> `--output`), and the `///` doc
+    comments become the help text shown by `--help`.
+    Settings that can also come from the config file are Options without a clap default, so
+    `effective_config` can tell whether the user actually passed them.
+    `global = true` lets a flag follow a subcommand too (`show 1234567 --output json`).
+*/
+#[derive(Parser)]
+struct Args {
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    /// Config file to read [default: ~/.config/read_ecs_table/config.toml]
+    #[arg(long, global = true)]
+    config: Option<PathBuf>,
+
+    /// Print the merged configuration (passwords redacted) and exit
+    #[arg(long)]
+    print_config: bool,
+
+    /// ODBC data source name to connect to [default: GECS_Testing]
+    #[arg(long, global = true, conflicts_with = "connection_string")]
+    dsn: Option<String>,
+
+    /// Full ODBC connection string, used as-is instead of --dsn
+    #[arg(long, global = true)]
+    connection_string: Option<String>,
+
+    /// Table to read, as table, schema.table or database.schema.table
+    /// [default: GECS_Testing.dbo.GECSEVENTS]
+    #[arg(long, global = true)]
+    table: Option<String>,
+
+    /// SQL to run instead of selecting everything from --table
+    #[arg(long, conflicts_with = "table")]
+    query: Option<String>,
+
+    /// Only events that began at or after this time: "2023-10-01", "2023-10-01 13:45:00", or an age such as 24h or 7d
+    #[arg(long, conflicts_with = "query", value_parser = timespec::parse)]
+    since: Option<chrono::NaiveDateTime>,
+
+    /// Only events that began before this time (same forms as --since)
+    #[arg(long, conflicts_with = "query", value_parser = timespec::parse)]
+    until: Option<chrono::NaiveDateTime>,
+
+    /// Read only the events whose eventnumbers are listed in this file, one per line ('#' starts a comment)
+    #[arg(long, conflicts_with = "query")]
+    eventnumbers_file: Option<PathBuf>,
+
+    /// How fetched events are written [default: from the --output-file extension, else the
+    /// config file, else text on a terminal and ndjson into a pipe]
+    #[arg(long, global = true, value_enum)]
+    output: Option<OutputFormat>,
+
+    /// Write to this file instead of stdout (it is created or truncated)
+    #[arg(long, global = true)]
+    output_file: Option<PathBuf>,
+
+    /// Fetch every column as text and parse it here, for drivers that misreport column types
+    #[arg(long, global = true)]
+    fetch_as_strings: bool,
+
+    /// Before reading rows, check the column names and types the driver reports against Event, and stop if they differ
+    #[arg(long)]
+    strict: bool,
+
+    /// What to do with a row that can't be read [default: abort, or skip when ndjson is chosen with --output or in the config file]
+    #[arg(long, value_enum)]
+    on_error: Option<OnError>,
+
+    /// Write every row that failed to read, with its raw values, to this CSV file (implies --fetch-as-strings)
+    #[arg(long)]
+    error_file: Option<PathBuf>,
+}
 
-type Result<T> = std::result::Result<T, Box<dyn Error>>;  
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum OnError {
+    /// Stop at the first bad row and exit with an error
+    Abort,
+    /// Report the bad row on stderr and carry on with the next one
+    Skip,
+}
 
-struct Event {
-    /*
-        Option<T> is an enum with two variants, Some(T) and None. 
-        It's a way of expressing that a value might be absent without resorting to null or special values. 
-    */
-    eventnumber: i32, // MSSQL Type: PK, int, not null
-    event_type: Option<u8>,  // MSSQL Type: tinyint, null - Using `event_type` instead of `type` because `type` is a keyword in Rust
-    server: Option<String>, // MSSQL Type: varchar(64), null
-    batch: Option<String>, // MSSQL Type: varchar(50), null
-    jobnum: Option<String>,  // MSSQL Type: varchar(50), null
-    submitted: Option<NaiveDateTime>,  // MSSQL Type: datetime, null
-    began: NaiveDateTime,  // MSSQL Type: PK, datetime, not null
-    ended: Option<NaiveDateTime>,  // MSSQL Type: datetime, null
-    message: Option<String>,  // MSSQL Type: varchar(255), null
-    status: Option<u8>,  // MSSQL Type: tinyint, null
-    priority: Option<u8>,  // MSSQL Type: tinyint, null
-    fixedby: Option<String>,  // MSSQL Type: varchar(48), null
-    fixcomment: Option<String>,  // MSSQL Type: varchar(255), null
-    color: Option<u8>,  // MSSQL Type: tinyint, null
-    bkcolor: Option<u8>,  // MSSQL Type: tinyint, null
-    beingworkedon: Option<u8>,  // MSSQL Type: varchar(48), null
-    dateclosed: Option<NaiveDateTime>,  // MSSQL Type: datetime, null
-    added: Option<NaiveDateTime>,  // MSSQL Type: datetime, null
+/*
+    Without a subcommand the tool exports the whole table (or --query) as before.
+*/
+#[derive(Subcommand)]
+enum Command {
+    /// Print one event in full, with the events around it from the same server, batch and job
+    Show {
+        /// Event number to look up
+        eventnumber: i32,
+
+        /// Which event to show when several share the eventnumber, e.g. "2023-10-01 13:45:00.003"
+        #[arg(long, value_parser = show::parse_began)]
+        began: Option<chrono::NaiveDateTime>,
+    },
+
+    /// Poll a few events until they have all ended (exit 0), any failed (2) or --timeout passed (3)
+    Watch {
+        /// Event numbers to follow
+        #[arg(required = true)]
+        eventnumbers: Vec<i32>,
+
+        /// Time between polls, e.g. 15s or 1m
+        #[arg(long, default_value = "15s", value_parser = timespec::parse_interval)]
+        interval: std::time::Duration,
+
+        /// Give up after this long, e.g. 30m or 2h [default: wait forever]
+        #[arg(long, value_parser = timespec::parse_interval)]
+        timeout: Option<std::time::Duration>,
+
+        /// Status codes that mean an event failed, e.g. --failed-status 4,5
+        #[arg(long, value_delimiter = ',')]
+        failed_status: Vec<u8>,
+    },
+}
+
+/*
+    main() itself only reports errors: returning Err from main would print it with {:?}, which
+    escapes the line breaks in multi-line messages such as TOML parse errors. error_chain
+    appends each underlying cause, e.g. the driver's diagnostic after "could not connect".
+*/
+fn main() {
+    if let Err(e) = run() {
+        eprintln!("Error: {}", error_chain(e.as_ref()));
+        process::exit(1);
+    }
 }
 
-fn main() -> Result<()> {
+fn run() -> Result<()> {
+    let args = Args::parse();
+    let file = config::load(args.config.as_deref())?;
+    // Asked for by name, on the command line or in the config file, rather than inferred.
+    let chosen_format = args.output.or(file.output);
+    let config = effective_config(&args, file, io::stdout().is_terminal());
+    if args.print_config {
+        let mut printable = config;
+        printable.connection_string = printable
+            .connection_string
+            .map(|s| config::redact_connection_string(&s));
+        print!("{}", toml::to_string(&printable)?);
+        process::exit(0);
+    }
+
+    let format = config.output.unwrap_or(OutputFormat::Text);
+
     // This is a 64 bit ODBC Connection and will not work on 32 bit systems.
-    let conn_str = "DSN=GECS_Testing;";
+    let conn_str = match &config.connection_string {
+        Some(conn_str) => conn_str.clone(),
+        None => dsn_connection_string(config.dsn.as_deref().unwrap_or(DEFAULT_DSN)),
+    };
+
+    let fetch_mode = if args.fetch_as_strings {
+        FetchMode::Strings
+    } else {
+        FetchMode::Typed
+    };
+
+    if args.command.is_some() {
+        if let Some(flag) = export_only_flag(&args) {
+            return Err(format!("{} only applies to the export; show and watch build their own queries", flag).into());
+        }
+    }
+    match args.command {
+        Some(Command::Show { eventnumber, began }) => {
+            let table = sql::ident::parse(config.table.as_deref().unwrap_or(DEFAULT_TABLE))?;
+            let out = open_output(config.output_file.as_deref())?;
+            return show::run(&conn_str, &table, eventnumber, began, fetch_mode, format, out);
+        }
+        Some(Command::Watch {
+            eventnumbers,
+            interval,
+            timeout,
+            failed_status,
+        }) => {
+            let table = sql::ident::parse(config.table.as_deref().unwrap_or(DEFAULT_TABLE))?;
+            let options = watch::Options {
+                interval,
+                timeout,
+                failed_statuses: &failed_status,
+                fetch_mode,
+            };
+            let out = open_output(config.output_file.as_deref())?;
+            let outcome = watch::run(&conn_str, &table, &eventnumbers, &options, format, out)?;
+            process::exit(outcome.exit_code());
+        }
+        None => {}
+    }
 
     /*
-    1. `Environment::new()`: This is calling a static method named `new` on the `Environment` struct (or type). 
-        This method typically creates and returns a new instance of the `Environment` type. In the context of ODBC, the `Environment` represents 
-        the ODBC environment which is a foundational setup needed to work with ODBC in an application. 
-    2. `.unwrap()`: This method is called on the `Result` or `Option` that `Environment::new()` returns. 
-        If `Environment::new()` returns an `Ok` variant of a `Result`, then `.unwrap()` extracts and returns the value inside the `Ok`. 
-        If it returns an `Err` variant (i.e., if there was an error creating the ODBC environment), then `.unwrap()` will panic and terminate the program.
-        In short, this line attempts to create a new ODBC environment, and if successful, binds it to the variable `env`. 
-        If there's any error in the creation process, the program will panic and terminate because of the `.unwrap()`.
+        Everything that can be checked, the connection included, is checked before --output-file
+        is opened, so a mistake on the command line doesn't truncate the last good export.
     */
-    let env = Environment::new().unwrap();
-    let conn = env.connect_with_connection_string(&conn_str)?;
+    let window = sql::Window {
+        since: args.since,
+        until: args.until,
+    };
+    if let (Some(since), Some(until)) = (window.since, window.until) {
+        if since >= until {
+            return Err(format!("--since ({}) must be earlier than --until ({})", since, until).into());
+        }
+    }
+    let mut requested = Vec::new();
+    let queries = match (&args.query, &args.eventnumbers_file) {
+        (Some(query), _) => vec![sql::raw(query)],
+        (None, Some(path)) => {
+            let table = sql::ident::parse(config.table.as_deref().unwrap_or(DEFAULT_TABLE))?;
+            requested = eventnumbers::read(path)?;
+            requested
+                .chunks(sql::MAX_IN_PARAMS)
+                .map(|ids| sql::select_by_eventnumbers(&table, ids, &window))
+                .collect()
+        }
+        (None, None) => {
+            let table = sql::ident::parse(config.table.as_deref().unwrap_or(DEFAULT_TABLE))?;
+            vec![sql::select_events(&table, &window)]
+        }
+    };
+
+    // Only text fetching keeps a failed row's raw values for --error-file to write out.
+    let fetch_mode = if args.error_file.is_some() && fetch_mode == FetchMode::Typed {
+        eprintln!("info: --error-file fetches every column as text, as --fetch-as-strings does, to keep failed rows' raw values");
+        FetchMode::Strings
+    } else {
+        fetch_mode
+    };
 
     /*
-        `Statement::with_parent(&conn)?` is a method call on the `Statement` type. In the context of ODBC:
-    1. `Statement`: In ODBC, a statement is an object that allows you to execute SQL commands and queries against a database. 
-        Once you have a connection to a database (represented by the `conn` variable), you can create one or more statements to interact with that database.
-    2. `with_parent(&conn)`: The `with_parent` method is used to create a new statement that is associated with a particular connection. 
-        The method takes a reference to a connection (`&conn` in this case) as its argument, indicating that the new statement will use that connection to communicate 
-        with the database.
-    3. `?`: This is the try operator in Rust. If `Statement::with_parent(&conn)` returns an `Ok` variant of a `Result`, the value inside that `Ok` is extracted. 
-        If it returns an `Err` variant (indicating an error occurred while creating the statement), then the error is returned early from the current function.
-        In essence, `let stmt = Statement::with_parent(&conn)?;` is trying to create a new ODBC statement associated with the given database connection, and if successful, 
-        binds it to the variable `stmt`. If there's an error, the current function will return early with that error.
-    4.  This means that stmt is an immutable binding to a Statement object.
+        Skipping is the default only for an NDJSON export the user asked for. One inferred from
+        a pipe, a redirect or a file name keeps the abort every other run has, so plain
+        `read-gecs-tables > out` doesn't start dropping rows.
     */
-    let stmt = Statement::with_parent(&conn)?;
-    let sql_text = "SELECT * FROM [GECS_Testing].[dbo].[GECSEVENTS];";
-
+    let on_error = args.on_error.unwrap_or(match chosen_format {
+        Some(OutputFormat::Ndjson) => OnError::Skip,
+        _ => OnError::Abort,
+    });
+
+    let env = create_environment()?;
+    let conn = connect(&env, &conn_str)?;
+    let mut writer = output::writer_for(format, open_output(config.output_file.as_deref())?)?;
+    let mut rejects = match &args.error_file {
+        Some(path) => Some(output::RejectWriter::create(path)?),
+        None => None,
+    };
+
+    let mut fetched = 0;
+    let mut skipped = 0;
+    let mut found = BTreeSet::new();
     /*
-        Function Call: The method 'exec_direct' is being called on the stmt object (which is an instance of Statement). 
-        This method attempts to directly execute a given SQL command or query represented by the string &sql_text.
-        Why does exec_direct take a string 'reference' &str instead of a string 'value' str?
-        The function doesn't need to own the string; it just needs to read it.
-        Also after the call to exec_direct, sql_text can still be used or modified in your code if needed.
-        By accepting a reference, the function can operate on the data without taking ownership, which can help prevent unnecessary allocations or data movements.
-        The Try Operator (?): After exec_direct is called, the ? operator is used. This operator checks the Result returned by exec_direct. 
-        If the Result is an Ok variant (indicating the operation was successful), it will extract the value inside the Ok for further use. 
-        If the Result is an Err variant (indicating an error occurred during the execution of the SQL statement), it will immediately return that error from the current function.
-        Pattern Matching with match: The value extracted from the Ok variant (or, in another way to think about it, the result of the successful execution of the SQL statement) 
-        is then passed into a match expression. A match expression in Rust is used for pattern matching: 
-        it allows you to check the value against several potential patterns and execute code based on which pattern the value matches.
-        In this specific case, it's likely that exec_direct returns a Result where the "successful" type can be one of two variants, 
-        probably something like Data (indicating that the SQL statement returned some data) and NoData (indicating that the SQL statement executed successfully 
-        but did not return any data, like an UPDATE or DELETE command in SQL might).
-        The code that follows the match expression will contain branches for each of these patterns, specifying what to do in each case.
-        Data() & NoData()
+        Batches run one after another and each comes back in eventnumber order, so the output
+        is in id order without collecting anything. Row numbers count across all batches.
     */
-
-    match stmt.exec_direct(&sql_text)? {
+    for (batch, query) in queries.iter().enumerate() {
+        let mut reader =
+            EventReader::with_params(&conn, &query.text, &query.params)?.fetch_mode(fetch_mode);
+        if !reader.has_result_set() {
+            eprintln!("Query executed, but no data returned.");
+        }
+        // Every batch runs the same SELECT, so checking the first one is enough.
+        if args.strict && batch == 0 {
+            let problems = reader.check_columns()?;
+            if !problems.is_empty() {
+                for problem in &problems {
+                    eprintln!("strict: {}", problem);
+                }
+                return Err(format!(
+                    "{} column problem(s) with the result set (see above); drop --strict to read it anyway",
+                    problems.len()
+                )
+                .into());
+            }
+        }
         /*
-            'mut stmt' Here, Data(mut stmt) is a pattern match on the Data variant of the result. 
-            The mut stmt inside the pattern means that if the result of exec_direct is the Data variant, 
-            then bind the value inside this variant to a new, mutable variable named stmt.
-            This effectively "shadows" the original immutable stmt. (let stmt = Statement::with_parent(&conn)?;)
-            Shadowing means that within the scope of the Data match arm, 
-            the name stmt refers to this new mutable variable, and not the original immutable one. 
-            This is a common pattern in Rust to transition from an immutable to a mutable variable without needing to come up with a new name.
-         */
-        Data(mut stmt) => {
-            while let Some(mut cursor) = stmt.fetch()? {
-                /*
-                    1. **`cursor.get_data(1)?`**: 
-                        - The `cursor` object represents a position within a result set from a database query.
-                        - The `get_data` method is being called on the `cursor` to retrieve the data from the first column of the current row, since indexing starts at 1 in this context.
-                        - The type of data that `get_data` returns is generic and can vary. In this context, it's expected to be an `Option<T>` where `T` is the type like `String`.
-                        - The `?` operator is used for error propagation in Rust. If `get_data` returns an error, the function will immediately return that error. 
-                          If `get_data` succeeds, it will give back the contained value from the `Ok` variant.
-                 */
-                let eventnumber_str: Option<String> = cursor.get_data(1)?;
-                let eventnumber: i32 = eventnumber_str.unwrap_or_default().parse()?;
-
-                let eventtype_str: Option<String> = cursor.get_data(2)?;
-                /*
-                    'and_then' method of Option<T>. 
-                    This method is useful when you want to transform the inner value of an Option (if there is one) and produce another Option.
-
-                    1. **`eventtype_str`**: An `Option<String>`. It may or may not contain a `String`.
-                    2. **`and_then`**: It calls the provided closure (the anonymous function) if there's a `Some(T)` value inside the `Option`. 
-                        Otherwise, if it's `None`, it does nothing and just returns `None`.
-                    3. **The Closure**: `|s| s.parse::<u8>().ok()`
-                        - `|s|`: This is an argument list. It declares a single argument `s` which represents the `String` inside `eventtype_str` (if there is one).
-                        - `s.parse::<u8>()`: Tries to parse the `String` as a `u8` value.
-                        - `.ok()`: Converts the `Result` returned by `parse` into an `Option`. 
-                        If the parsing is successful, it'll produce `Some(u8)`. If there's an error, it'll produce `None`.
-
-                        The anonymous function more verbosely without using a closure, it might look like this:
-
-                        fn parse_u8_from_string(s: String) -> Option<u8> {
-                            match s.parse::<u8>() {
-                                Ok(value) => Some(value),
-                                Err(_) => None
-                            }
+            `while let` calls next() by hand instead of handing the reader to a for loop, so it
+            is still ours inside the loop (to collect each row's unparsed values) and after it
+            (to ask how many rows it fetched).
+        */
+        while let Some(event) = reader.next() {
+            match event {
+                Ok(event) => {
+                    for unparsed in reader.take_unparsed() {
+                        eprintln!(
+                            "warning: row {}: {}; treating it as NULL",
+                            fetched + reader.rows_fetched(),
+                            unparsed
+                        );
+                    }
+                    if !requested.is_empty() {
+                        found.insert(event.eventnumber);
+                    }
+                    writer.write(event)?
+                }
+                Err(ReadEcsError::Row { row, raw, source }) => {
+                    let row = fetched + row;
+                    // The row exists even though it couldn't be read, so its id was found.
+                    if let Some(eventnumber) = source.eventnumber() {
+                        found.insert(eventnumber);
+                    }
+                    if let Some(rejects) = rejects.as_mut() {
+                        rejects.write(row, source.as_ref(), &raw)?;
+                    }
+                    if on_error == OnError::Abort {
+                        if let Some(rejects) = rejects.as_mut() {
+                            rejects.finish()?;
                         }
-
-                        let event_type = if let Some(inner_string) = eventtype_str {
-                            parse_u8_from_string(inner_string)
-                        } else {
-                            None
-                        };
-                 */
-                let event_type = eventtype_str.and_then(|s| s.parse::<u8>().ok());
-
-                let server: Option<String> = cursor.get_data(3)?;
-
-                let batch: Option<String> = cursor.get_data(4)?;
-
-                let jobnum: Option<String> = cursor.get_data(5)?;
-
-                let submitted_str: Option<String> = cursor.get_data(6)?;
-                let submitted = submitted_str
-                    .and_then(|s| NaiveDateTime::parse_from_str(&s, "%Y-%m-%d %H:%M:%S").ok());
-
-                let began_str: String = cursor.get_data(7)?.ok_or("Missing value for 'began'")?;
-                let began = NaiveDateTime::parse_from_str(&began_str, "%Y-%m-%d %H:%M:%S%.f")?;
-
-                let ended_str: Option<String> = cursor.get_data(8)?;
-                let ended = ended_str
-                    .and_then(|s| NaiveDateTime::parse_from_str(&s, "%Y-%m-%d %H:%M:%S").ok());
-
-                let message: Option<String> = cursor.get_data(9)?;
-
-                let status_str: Option<String> = cursor.get_data(10)?;
-                let status = status_str.and_then(|s| s.parse::<u8>().ok());
-
-                let priority_str: Option<String> = cursor.get_data(11)?;
-                let priority = priority_str.and_then(|s| s.parse::<u8>().ok());
-
-                let fixedby: Option<String> = cursor.get_data(12)?;
-
-                let fixcomment: Option<String> = cursor.get_data(13)?;
-
-                let color_str: Option<String> = cursor.get_data(14)?;
-                let color = color_str.and_then(|s| s.parse::<u8>().ok());
-
-                let bkcolor_str: Option<String> = cursor.get_data(15)?;
-                let bkcolor = bkcolor_str.and_then(|s| s.parse::<u8>().ok());
-
-                let beingworkedon_str: Option<String> = cursor.get_data(16)?;
-                let beingworkedon = beingworkedon_str.and_then(|s| s.parse::<u8>().ok());
-
-                let dateclosed_str: Option<String> = cursor.get_data(17)?;
-                let dateclosed = dateclosed_str
-                    .and_then(|s| NaiveDateTime::parse_from_str(&s, "%Y-%m-%d %H:%M:%S").ok());
-
-                let added_str: Option<String> = cursor.get_data(18)?;
-                let added = added_str
-                    .and_then(|s| NaiveDateTime::parse_from_str(&s, "%Y-%m-%d %H:%M:%S").ok());
-
-                let event = Event {
-                    eventnumber: eventnumber,
-                    event_type: event_type,
-                    server: server,
-                    batch: batch,
-                    jobnum: jobnum,
-                    message: message,
-                    status: status,
-                    priority: priority,
-                    fixedby: fixedby,
-                    fixcomment: fixcomment,
-                    color: color,
-                    bkcolor: bkcolor,
-                    beingworkedon: beingworkedon,
-                    submitted: submitted,
-                    began: began,
-                    ended: ended,
-                    dateclosed: dateclosed,
-                    added: added,
-                    // submitted: cursor.get_data(6)?,
-                    // began: cursor.get_data(7)?,
-                    // ended: cursor.get_data(8)?,
-                    // dateclosed: cursor.get_data(17)?,
-                    // added: cursor.get_data(18)?.unwrap_or_default(),
-                };
-
-                match event.event_type {
-                    Some(t) => println!("Event Type: {}", t),
-                    None => println!("Event Type: NULL"),
+                        return Err(ReadEcsError::Row { row, raw, source }.into());
+                    }
+                    skipped += 1;
+                    writer.row_error(row, source.as_ref())?;
                 }
+                Err(fatal) => return Err(fatal.into()),
+            }
+        }
+        fetched += reader.rows_fetched();
+    }
+    writer.finish()?;
+    if let Some(rejects) = rejects.as_mut() {
+        rejects.finish()?;
+    }
+    if on_error == OnError::Skip {
+        eprintln!("fetched {} rows, skipped {}", fetched, skipped);
+    }
 
-                println!("Event Number: {}", event.eventnumber);
-                println!("Server: {}", event.server.unwrap_or("NULL".to_string()));
-                println!("Batch: {}", event.batch.unwrap_or("NULL".to_string()));
-                println!("Job Number: {}", event.jobnum.unwrap_or("NULL".to_string()));
-                println!(
-                    "Submitted: {}",
-                    event
-                        .submitted
-                        .map_or("NULL".to_string(), |d| d.to_string())
-                );
-                println!("Began: {}", event.began);
-                println!(
-                    "Ended: {}",
-                    event.ended.map_or("NULL".to_string(), |d| d.to_string())
-                );
-                println!("Message: {}", event.message.unwrap_or("NULL".to_string()));
-                println!(
-                    "Status: {}",
-                    event.status.map_or("NULL".to_string(), |d| d.to_string())
-                );
-                println!(
-                    "Priority: {}",
-                    event.priority.map_or("NULL".to_string(), |d| d.to_string())
-                );
-                println!(
-                    "Fixed By: {}",
-                    event.fixedby.map_or("NULL".to_string(), |d| d.to_string())
-                );
-                println!(
-                    "Fix Comment: {}",
-                    event
-                        .fixcomment
-                        .map_or("NULL".to_string(), |d| d.to_string())
-                );
-                println!(
-                    "Color: {}",
-                    event.color.map_or("NULL".to_string(), |d| d.to_string())
-                );
-                println!(
-                    "BkColor: {}",
-                    event.bkcolor.map_or("NULL".to_string(), |d| d.to_string())
-                );
-                println!(
-                    "Being Worked On: {}",
-                    event
-                        .beingworkedon
-                        .map_or("NULL".to_string(), |d| d.to_string())
-                );
-                println!(
-                    "Date Closed: {}",
-                    event
-                        .dateclosed
-                        .map_or("NULL".to_string(), |d| d.to_string())
-                );
-                println!(
-                    "Added: {}",
-                    /*
-                        map_or: This is a method on the Option type. It takes two arguments:
-                        A default value ("NULL".to_string() in this case) to be used if the Option is None.
-                        A closure (|d| d.to_string()) to be applied if the Option is Some.
-                        If event.added is Some(d), then d.to_string() will be executed, converting the NaiveDateTime to a String.
-                        If event.added is None, then "NULL".to_string() will be returned.
-                        In simpler terms, this expression is converting the NaiveDateTime to a String if it exists. 
-                        If it doesn't exist (i.e., it's None), then the string "NULL" is returned.
-                    */
-                    /*
-                        In Rust, a closure is a way to define an anonymous function. 
-                        It's called "closure" because it can "close over" variables from its surrounding scope, capturing them in its environment.
-                        Here are some characteristics and examples of closures in Rust:
-                        Basic Usage: Closures are often used as arguments to functions, especially for short, "throw-away" functions that you don't want to name.
-                            let numbers = vec![1, 2, 3, 4, 5];
-                            let squared: Vec<_> = numbers.iter().map(|x| x * x).collect();
-                            println!("{:?}", squared); // [1, 4, 9, 16, 25]
-                        In the example above, |x| x * x is a closure that takes a value x and returns its square.
-                        Environment Capture: Closures can capture values from their surrounding environment.
-                            let multiplier = 2;
-                            let multiply_by = |x| x * multiplier;
-                            println!("{}", multiply_by(10)); // 20
-                        In this example, the closure multiply_by captures the multiplier variable from its surrounding environment.
-                        Types of Capture: Closures can capture variables in their environment in different ways:
-                            By reference: |&x|
-                            By mutable reference: |&mut x|
-                            By value (moving the value): |x|
-                            Type Inference: One advantage of closures in Rust is that they can have inferred input and return types, so you often don't need to annotate them.
-                        Flexibility with Parameters and Body: Like functions, closures can take multiple parameters, and their body can have multiple statements.
-                                let greeting = |name, time_of_day| {
-                                println!("Hello, {}", name);
-                                println!("Good {}", time_of_day);
-                            };
-                            greeting("Alice", "morning");
-                        Fn, FnMut, and FnOnce: Rust has three traits to represent how a closure captures variables from its environment: Fn, FnMut, and FnOnce. Each one allows different types of manipulation of the captured environment:
-                            Fn: borrows values immutably.
-                            FnMut: borrows values mutably.
-                            FnOnce: takes ownership of the environment.
-                            In essence, closures provide a convenient way to define small, anonymous bits of functionality inline, with the added power of capturing their environment. They are especially useful for higher-order functions, callback-style functions, and any situation where a small, specialized bit of logic is needed.
-                    */
-                    event.added.map_or("NULL".to_string(), |d| d.to_string())
+    let missing: Vec<String> = requested
+        .iter()
+        .filter(|id| !found.contains(*id))
+        .map(|id| id.to_string())
+        .collect();
+    if !missing.is_empty() {
+        let place = if window.since.is_some() || window.until.is_some() {
+            " in the --since/--until window"
+        } else {
+            ""
+        };
+        eprintln!(
+            "{} of {} requested eventnumbers were not found{}: {}",
+            missing.len(),
+            requested.len(),
+            place,
+            missing.join(", ")
+        );
+    }
+
+    Ok(())
+}
+
+/*
+    Merges the command line over the config file over the built-in defaults, field by field.
+    The DSN and the connection string are treated as one setting: giving either on the command
+    line replaces whichever of the two the config file had.
+    The output format is the exception to "command line, then file": a recognised --output-file
+    extension comes between the two, because `--output-file events.csv` says what the user wants
+    more directly than a format the config file set for every run. An --output-file whose
+    extension names no format gets text. With nothing else to go on, stdout gets text on a
+    terminal and NDJSON when it goes into a pipe or a redirect. All of that is for the export:
+    show and watch write text unless --output says otherwise.
+    Any format that wasn't given explicitly is reported on stderr, so the choice is never a
+    surprise.
+*/
+fn effective_config(args: &Args, file: Config, stdout_is_terminal: bool) -> Config {
+    let (dsn, connection_string) = if args.dsn.is_some() || args.connection_string.is_some() {
+        (args.dsn.clone(), args.connection_string.clone())
+    } else if file.dsn.is_some() || file.connection_string.is_some() {
+        (file.dsn, file.connection_string)
+    } else {
+        (Some(DEFAULT_DSN.to_string()), None)
+    };
+
+    let output_file = args.output_file.clone().or(file.output_file);
+    Config {
+        dsn,
+        connection_string,
+        table: args
+            .table
+            .clone()
+            .or(file.table)
+            .or_else(|| Some(DEFAULT_TABLE.to_string())),
+        output: Some(infer_output(args, file.output, output_file.as_deref(), stdout_is_terminal)),
+        output_file,
+    }
+}
+
+fn infer_output(
+    args: &Args,
+    configured: Option<OutputFormat>,
+    output_file: Option<&Path>,
+    stdout_is_terminal: bool,
+) -> OutputFormat {
+    if let Some(format) = args.output {
+        return format;
+    }
+    /*
+        show and watch print for a person to read, even through `| less`, whatever the export
+        is set up to write; the config file's format and the file name are for the export.
+    */
+    if args.command.is_some() {
+        return OutputFormat::Text;
+    }
+    if let Some(path) = output_file {
+        match output::format_for_path(path) {
+            Some(format) => {
+                eprintln!("info: writing {} output, from the name of '{}'", format, path.display());
+                return format;
+            }
+            None if configured.is_none() => {
+                eprintln!(
+                    "info: no output format is known for '{}'; writing text (use --output to choose)",
+                    path.display()
                 );
-                println!();
+                return OutputFormat::Text;
             }
+            None => {}
         }
-        NoData(_) => eprintln!("Query executed, but no data returned."),
     }
+    if let Some(format) = configured {
+        return format;
+    }
+    if output_file.is_some() || stdout_is_terminal {
+        OutputFormat::Text
+    } else {
+        eprintln!("info: stdout is not a terminal; writing ndjson output (use --output to choose)");
+        OutputFormat::Ndjson
+    }
+}
 
-    Ok(())
+/*
+    The first flag given that only the export reads. They aren't global, so clap already refuses
+    them after a subcommand; this catches them written before one (`--since 24h show 1`), where
+    they would otherwise be ignored without a word.
+*/
+fn export_only_flag(args: &Args) -> Option<&'static str> {
+    [
+        ("--query", args.query.is_some()),
+        ("--since", args.since.is_some()),
+        ("--until", args.until.is_some()),
+        ("--eventnumbers-file", args.eventnumbers_file.is_some()),
+        ("--strict", args.strict),
+        ("--on-error", args.on_error.is_some()),
+        ("--error-file", args.error_file.is_some()),
+    ]
+    .into_iter()
+    .find_map(|(flag, given)| given.then_some(flag))
 }
 
-// use tiberius::{Client, Config, QueryItem};
-// use tokio::net::TcpStream;
-// use tokio_util::compat::TokioAsyncReadCompatExt;
-// use futures::stream::StreamExt;
-// extern crate odbc;
-// use odbc::*;
+// --output-file, created or truncated, or stdout.
+fn open_output(path: Option<&Path>) -> Result<Box<dyn Write>> {
+    Ok(match path {
+        Some(path) => {
+            let file = File::create(path)
+                .map_err(|e| format!("cannot create '{}': {}", path.display(), e))?;
+            Box::new(BufWriter::new(file))
+        }
+        None => Box::new(io::stdout().lock()),
+    })
+}
 
-// #[tokio::main]
-// async fn main() -> Result<(), Box<dyn std::error::Error>> {
-//     // Hardcoded connection string for testing. NEVER do this in production or in shared code.
-//     let conn_str = "server=tcp:MINI\\BEESERVER,1433;user id=sa;password=jollyroger1;initial catalog=GECS_Testing;trustServerCertificate=true;";
+/*
+    ODBC connection strings are `key=value;` pairs, so a DSN containing `;`, `{`, `}` or `=`
+    has to be wrapped in braces, with any `}` inside doubled.
+*/
+fn dsn_connection_string(dsn: &str) -> String {
+    if dsn.contains([';', '{', '}', '=']) {
+        format!("DSN={{{}}};", dsn.replace('}', "}}"))
+    } else {
+        format!("DSN={};", dsn)
+    }
+}
 
-//     let config = Config::from_ado_string(&conn_str)?;
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-//     // Create a tokio TcpStream
-//     let tokio_stream = TcpStream::connect(config.get_addr()).await?;
+    fn args(argv: &[&str]) -> Args {
+        Args::try_parse_from(std::iter::once("read-gecs-tables").chain(argv.iter().copied())).unwrap()
+    }
 
-//     // Convert the tokio TcpStream to a compatible stream
-//     let compat_stream = tokio_stream.compat();
+    #[test]
+    fn output_flag_beats_everything() {
+        let args = args(&["--output", "csv"]);
+        assert_eq!(
+            infer_output(&args, Some(OutputFormat::Text), Some(Path::new("events.json")), false),
+            OutputFormat::Csv
+        );
+    }
 
-//     // Now use the compat_stream to connect with tiberius
-//     let mut client = Client::connect(config, compat_stream).await?;
+    #[test]
+    fn file_extension_beats_the_config_file() {
+        let args = args(&[]);
+        assert_eq!(
+            infer_output(&args, Some(OutputFormat::Csv), Some(Path::new("events.json")), false),
+            OutputFormat::Json
+        );
+    }
 
-//     println!("Connected!");
+    #[test]
+    fn unknown_extension_falls_back_to_the_config_file() {
+        let args = args(&[]);
+        assert_eq!(
+            infer_output(&args, Some(OutputFormat::Csv), Some(Path::new("events.dat")), false),
+            OutputFormat::Csv
+        );
+    }
+
+    #[test]
+    fn unknown_extension_without_config_is_text() {
+        let args = args(&[]);
+        assert_eq!(infer_output(&args, None, Some(Path::new("events.dat")), false), OutputFormat::Text);
+        assert_eq!(infer_output(&args, None, Some(Path::new("events")), false), OutputFormat::Text);
+    }
+
+    #[test]
+    fn config_file_applies_to_stdout() {
+        let args = args(&[]);
+        assert_eq!(infer_output(&args, Some(OutputFormat::Ndjson), None, false), OutputFormat::Ndjson);
+    }
+
+    // Whatever stdout is, a subcommand's output is for a person.
+    #[test]
+    fn terminal_gets_text() {
+        assert_eq!(infer_output(&args(&[]), None, None, true), OutputFormat::Text);
+    }
+
+    #[test]
+    fn pipe_gets_ndjson() {
+        assert_eq!(infer_output(&args(&[]), None, None, false), OutputFormat::Ndjson);
+    }
+
+    // A file is read by a person as often as by a program; without an extension it's text.
+    #[test]
+    fn output_file_is_text_whatever_stdout_is() {
+        assert_eq!(infer_output(&args(&[]), None, Some(Path::new("events")), false), OutputFormat::Text);
+    }
 
-//     let mut query_stream = client.simple_query("SELECT column_name FROM your_table").await?;
+    #[test]
+    fn subcommands_default_to_text() {
+        let args = args(&["show", "1234567"]);
+        assert_eq!(infer_output(&args, None, None, false), OutputFormat::Text);
+    }
+
+    // The config file's format is for the export; show and watch would reject most of them.
+    #[test]
+    fn subcommands_ignore_the_configured_format() {
+        for argv in [&["show", "1234567"][..], &["watch", "1234567"][..]] {
+            let args = args(argv);
+            assert_eq!(infer_output(&args, Some(OutputFormat::Ndjson), None, false), OutputFormat::Text);
+            assert_eq!(
+                infer_output(&args, Some(OutputFormat::Csv), Some(Path::new("events.csv")), false),
+                OutputFormat::Text
+            );
+        }
+    }
 
-//     while let Some(item_result) = query_stream.next().await {
-//         match item_result {
-//             Ok(QueryItem::Row(row)) => {
-//                 let column: &str = row.get(0).unwrap();
-//                 println!("{}", column);
-//             },
-//             Ok(_) => {},  // Handle other types of QueryItem if needed
-//             Err(e) => {
-//                 eprintln!("Error processing row: {}", e);
-//             }
-//         }
-//     }
+    #[test]
+    fn subcommands_take_an_explicit_output() {
+        let args = args(&["show", "1234567", "--output", "json"]);
+        assert_eq!(infer_output(&args, Some(OutputFormat::Ndjson), None, false), OutputFormat::Json);
+    }
 
-//     Ok(())
-// }
+    #[test]
+    fn export_flags_are_found_before_a_subcommand() {
+        assert_eq!(export_only_flag(&args(&["show", "1"])), None);
+        assert_eq!(export_only_flag(&args(&["--since", "24h", "show", "1"])), Some("--since"));
+        assert_eq!(
+            export_only_flag(&args(&["--eventnumbers-file", "ids.txt", "watch", "1"])),
+            Some("--eventnumbers-file")
+        );
+        assert_eq!(export_only_flag(&args(&["--strict", "show", "1"])), Some("--strict"));
+        assert_eq!(export_only_flag(&args(&["--on-error", "skip", "show", "1"])), Some("--on-error"));
+        assert_eq!(export_only_flag(&args(&["--error-file", "bad.csv", "show", "1"])), Some("--error-file"));
+        assert_eq!(export_only_flag(&args(&["--query", "select 1", "show", "1"])), Some("--query"));
+    }
+
+    // Flags every command reads are still fine.
+    #[test]
+    fn shared_flags_are_not_export_only() {
+        let args = args(&["--fetch-as-strings", "--table", "dbo.t", "show", "1", "--output", "json"]);
+        assert_eq!(export_only_flag(&args), None);
+    }
+
+    #[test]
+    fn output_file_flag_replaces_the_config_file_one() {
+        let file = Config {
+            output_file: Some(PathBuf::from("configured.csv")),
+            ..Config::default()
+        };
+        let config = effective_config(&args(&["--output-file", "events.ndjson"]), file, false);
+        assert_eq!(config.output_file, Some(PathBuf::from("events.ndjson")));
+        assert_eq!(config.output, Some(OutputFormat::Ndjson));
+    }
+}
@@ -0,0 +1,463 @@
+use std::error::Error;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+use chrono::NaiveDateTime;
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+
+use crate::Result;
+use read_gecs_tables::{error_chain, Event, RawRow, COLUMNS, COLUMN_COUNT};
+
+// The string form SQL Server itself uses for datetime values, e.g. "2023-10-01 13:45:00.000".
+const SQL_DATETIME_FORMAT: &str = "%Y-%m-%d %H:%M:%S%.3f";
+
+// Column names in result set order. They match the JSON keys, which serde takes from Event.
+fn column_names() -> [&'static str; COLUMN_COUNT as usize] {
+    COLUMNS.map(|column| column.name)
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputFormat {
+    /// One "Label: value" line per column, blank line between events
+    Text,
+    /// A single JSON array of event objects; NULL columns are JSON null
+    Json,
+    /// A header row, then one row per event; NULL columns are empty fields
+    Csv,
+    /// One JSON object per line, written as each row is fetched
+    Ndjson,
+}
+
+// The name used on the command line and in the config file, e.g. "ndjson".
+impl std::fmt::Display for OutputFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.to_possible_value() {
+            Some(value) => f.write_str(value.get_name()),
+            None => Ok(()),
+        }
+    }
+}
+
+/*
+    The format an --output-file name implies, when --output isn't given. ".json" means a JSON
+    array; use ".ndjson" or ".jsonl" for one object per line. Other extensions (.tsv, .xlsx,
+    .parquet, .yaml, anything compressed) have no writer here, so they imply nothing.
+*/
+pub fn format_for_path(path: &Path) -> Option<OutputFormat> {
+    let extension = path.extension()?.to_str()?.to_lowercase();
+    match extension.as_str() {
+        "txt" => Some(OutputFormat::Text),
+        "json" => Some(OutputFormat::Json),
+        "csv" => Some(OutputFormat::Csv),
+        "ndjson" | "jsonl" => Some(OutputFormat::Ndjson),
+        _ => None,
+    }
+}
+
+/*
+    Every output format implements this trait so the fetch loop in main() doesn't need to know
+    which one is active. `write` is called once per fetched row and `finish` once after the last
+    row (or straight away when the query returned nothing), which gives formats that need a
+    closing bracket or a final flush a place to do it.
+    `row_error` is called instead of `write` when a row couldn't be turned into an Event and
+    --on-error skip is in effect. The row is dropped either way; the method only decides how it
+    is reported on stderr.
+    Box<dyn EventWriter> lets `writer_for` pick the implementation at runtime from the CLI flag,
+    and Box<dyn Write> lets every format target either stdout or a file the same way.
+*/
+pub trait EventWriter {
+    fn write(&mut self, event: Event) -> Result<()>;
+    fn finish(&mut self) -> Result<()>;
+
+    fn row_error(&mut self, row: u64, error: &dyn Error) -> Result<()> {
+        eprintln!("skipping row {}: {}", row, error_chain(error));
+        Ok(())
+    }
+}
+
+pub fn writer_for(format: OutputFormat, out: Box<dyn Write>) -> Result<Box<dyn EventWriter>> {
+    Ok(match format {
+        OutputFormat::Text => Box::new(TextWriter { out }),
+        OutputFormat::Json => Box::new(JsonWriter {
+            out,
+            events: Vec::new(),
+        }),
+        OutputFormat::Csv => {
+            let mut csv = csv::Writer::from_writer(out);
+            csv.write_record(column_names())?;
+            Box::new(CsvWriter { csv })
+        }
+        OutputFormat::Ndjson => Box::new(NdjsonWriter { out }),
+    })
+}
+
+/*
+    The labeled "Field: value" block for one event, shared by TextWriter and `show`.
+*/
+pub fn write_text(out: &mut dyn Write, event: Event) -> Result<()> {
+    match event.event_type {
+        Some(t) => writeln!(out, "Event Type: {}", t)?,
+        None => writeln!(out, "Event Type: NULL")?,
+    }
+
+    writeln!(out, "Event Number: {}", event.eventnumber)?;
+    writeln!(out, "Server: {}", event.server.unwrap_or("NULL".to_string()))?;
+    writeln!(out, "Batch: {}", event.batch.unwrap_or("NULL".to_string()))?;
+    writeln!(out, "Job Number: {}", event.jobnum.unwrap_or("NULL".to_string()))?;
+    writeln!(
+        out,
+        "Submitted: {}",
+        event
+            .submitted
+            .map_or("NULL".to_string(), |d| d.to_string())
+    )?;
+    writeln!(out, "Began: {}", event.began)?;
+    writeln!(
+        out,
+        "Ended: {}",
+        event.ended.map_or("NULL".to_string(), |d| d.to_string())
+    )?;
+    writeln!(out, "Message: {}", event.message.unwrap_or("NULL".to_string()))?;
+    writeln!(
+        out,
+        "Status: {}",
+        event.status.map_or("NULL".to_string(), |d| d.to_string())
+    )?;
+    writeln!(
+        out,
+        "Priority: {}",
+        event.priority.map_or("NULL".to_string(), |d| d.to_string())
+    )?;
+    writeln!(
+        out,
+        "Fixed By: {}",
+        event.fixedby.map_or("NULL".to_string(), |d| d.to_string())
+    )?;
+    writeln!(
+        out,
+        "Fix Comment: {}",
+        event
+            .fixcomment
+            .map_or("NULL".to_string(), |d| d.to_string())
+    )?;
+    writeln!(
+        out,
+        "Color: {}",
+        event.color.map_or("NULL".to_string(), |d| d.to_string())
+    )?;
+    writeln!(
+        out,
+        "BkColor: {}",
+        event.bkcolor.map_or("NULL".to_string(), |d| d.to_string())
+    )?;
+    writeln!(
+        out,
+        "Being Worked On: {}",
+        event
+            .beingworkedon
+            .map_or("NULL".to_string(), |d| d.to_string())
+    )?;
+    writeln!(
+        out,
+        "Date Closed: {}",
+        event
+            .dateclosed
+            .map_or("NULL".to_string(), |d| d.to_string())
+    )?;
+    writeln!(
+        out,
+        "Added: {}",
+        /*
+            map_or: This is a method on the Option type. It takes two arguments:
+            A default value ("NULL".to_string() in this case) to be used if the Option is None.
+            A closure (|d| d.to_string()) to be applied if the Option is Some.
+            If event.added is Some(d), then d.to_string() will be executed, converting the NaiveDateTime to a String.
+            If event.added is None, then "NULL".to_string() will be returned.
+            In simpler terms, this expression is converting the NaiveDateTime to a String if it exists. 
+            If it doesn't exist (i.e., it's None), then the string "NULL" is returned.
+        */
+        /*
+            In Rust, a closure is a way to define an anonymous function. 
+            It's called "closure" because it can "close over" variables from its surrounding scope, capturing them in its environment.
+            Here are some characteristics and examples of closures in Rust:
+            Basic Usage: Closures are often used as arguments to functions, especially for short, "throw-away" functions that you don't want to name.
+                let numbers = vec![1, 2, 3, 4, 5];
+                let squared: Vec<_> = numbers.iter().map(|x| x * x).collect();
+                println!("{:?}", squared); // [1, 4, 9, 16, 25]
+            In the example above, |x| x * x is a closure that takes a value x and returns its square.
+            Environment Capture: Closures can capture values from their surrounding environment.
+                let multiplier = 2;
+                let multiply_by = |x| x * multiplier;
+                println!("{}", multiply_by(10)); // 20
+            In this example, the closure multiply_by captures the multiplier variable from its surrounding environment.
+            Types of Capture: Closures can capture variables in their environment in different ways:
+                By reference: |&x|
+                By mutable reference: |&mut x|
+                By value (moving the value): |x|
+                Type Inference: One advantage of closures in Rust is that they can have inferred input and return types, so you often don't need to annotate them.
+            Flexibility with Parameters and Body: Like functions, closures can take multiple parameters, and their body can have multiple statements.
+                    let greeting = |name, time_of_day| {
+                    println!("Hello, {}", name);
+                    println!("Good {}", time_of_day);
+                };
+                greeting("Alice", "morning");
+            Fn, FnMut, and FnOnce: Rust has three traits to represent how a closure captures variables from its environment: Fn, FnMut, and FnOnce. Each one allows different types of manipulation of the captured environment:
+                Fn: borrows values immutably.
+                FnMut: borrows values mutably.
+                FnOnce: takes ownership of the environment.
+                In essence, closures provide a convenient way to define small, anonymous bits of functionality inline, with the added power of capturing their environment. They are especially useful for higher-order functions, callback-style functions, and any situation where a small, specialized bit of logic is needed.
+        */
+        event.added.map_or("NULL".to_string(), |d| d.to_string())
+    )?;
+    writeln!(out)?;
+    Ok(())
+}
+
+struct TextWriter {
+    out: Box<dyn Write>,
+}
+
+impl EventWriter for TextWriter {
+    fn write(&mut self, event: Event) -> Result<()> {
+        write_text(&mut self.out, event)
+    }
+
+    fn finish(&mut self) -> Result<()> {
+        self.out.flush()?;
+        Ok(())
+    }
+}
+
+/*
+    A JSON array can only be closed once we know there are no more rows, so this writer keeps
+    the events until `finish`. The field names and the `type` rename come from the
+    #[derive(Serialize)] on Event; Option fields serialize as null and NaiveDateTime as an
+    ISO 8601 string ("2023-10-01T13:45:00").
+*/
+struct JsonWriter {
+    out: Box<dyn Write>,
+    events: Vec<Event>,
+}
+
+impl EventWriter for JsonWriter {
+    fn write(&mut self, event: Event) -> Result<()> {
+        self.events.push(event);
+        Ok(())
+    }
+
+    fn finish(&mut self) -> Result<()> {
+        serde_json::to_writer_pretty(&mut self.out, &self.events)?;
+        writeln!(self.out)?;
+        self.out.flush()?;
+        Ok(())
+    }
+}
+
+/*
+    The csv crate takes care of quoting: any field containing a comma, a double quote or a
+    line break is wrapped in quotes with inner quotes doubled, so message and fixcomment text
+    round-trips into a spreadsheet intact.
+*/
+struct CsvWriter {
+    csv: csv::Writer<Box<dyn Write>>,
+}
+
+fn csv_field<T: ToString>(value: &Option<T>) -> String {
+    value.as_ref().map_or(String::new(), |v| v.to_string())
+}
+
+fn csv_datetime(value: &Option<NaiveDateTime>) -> String {
+    value.map_or(String::new(), |d| d.format(SQL_DATETIME_FORMAT).to_string())
+}
+
+impl EventWriter for CsvWriter {
+    fn write(&mut self, event: Event) -> Result<()> {
+        self.csv.write_record([
+            event.eventnumber.to_string(),
+            csv_field(&event.event_type),
+            csv_field(&event.server),
+            csv_field(&event.batch),
+            csv_field(&event.jobnum),
+            csv_datetime(&event.submitted),
+            event.began.format(SQL_DATETIME_FORMAT).to_string(),
+            csv_datetime(&event.ended),
+            csv_field(&event.message),
+            csv_field(&event.status),
+            csv_field(&event.priority),
+            csv_field(&event.fixedby),
+            csv_field(&event.fixcomment),
+            csv_field(&event.color),
+            csv_field(&event.bkcolor),
+            csv_field(&event.beingworkedon),
+            csv_datetime(&event.dateclosed),
+            csv_datetime(&event.added),
+        ])?;
+        Ok(())
+    }
+
+    fn finish(&mut self) -> Result<()> {
+        self.csv.flush()?;
+        Ok(())
+    }
+}
+
+/*
+    NDJSON needs no surrounding brackets, so each event is serialized and written the moment it
+    is fetched and nothing is held in memory. Output to a file goes through a BufWriter, which
+    gathers serde_json's small writes into one per line; each line is then flushed straight away,
+    so `tail -f` or a downstream reader sees every row while the query is still running, even
+    when the next one is slow to arrive.
+    A row that fails to map is reported as a one-line JSON object on stderr and skipped, so one
+    bad value doesn't throw away the rest of a long export; that's why --on-error defaults to
+    skip when NDJSON is asked for by name rather than inferred.
+*/
+struct NdjsonWriter {
+    out: Box<dyn Write>,
+}
+
+impl EventWriter for NdjsonWriter {
+    fn write(&mut self, event: Event) -> Result<()> {
+        serde_json::to_writer(&mut self.out, &event)?;
+        writeln!(self.out)?;
+        self.out.flush()?;
+        Ok(())
+    }
+
+    fn finish(&mut self) -> Result<()> {
+        self.out.flush()?;
+        Ok(())
+    }
+
+    fn row_error(&mut self, row: u64, error: &dyn Error) -> Result<()> {
+        eprintln!("{}", serde_json::json!({ "row": row, "error": error_chain(error) }));
+        Ok(())
+    }
+}
+
+/*
+    --error-file: every row that failed to map, as CSV. Each record starts with the row number
+    and the error, followed by the columns exactly as the driver returned them, so the file can
+    be opened next to the source table to see what was wrong. NULL and columns that couldn't
+    be read at all are empty fields. Raw text only exists when every column is fetched as text,
+    which is why --error-file turns on --fetch-as-strings.
+*/
+pub struct RejectWriter {
+    csv: csv::Writer<BufWriter<File>>,
+}
+
+impl RejectWriter {
+    pub fn create(path: &Path) -> Result<Self> {
+        let file = File::create(path)
+            .map_err(|e| format!("cannot create '{}': {}", path.display(), e))?;
+        let mut csv = csv::Writer::from_writer(BufWriter::new(file));
+        let mut header = vec!["row", "error"];
+        header.extend(column_names());
+        csv.write_record(header)?;
+        Ok(RejectWriter { csv })
+    }
+
+    pub fn write(&mut self, row: u64, error: &dyn Error, raw: &RawRow) -> Result<()> {
+        let mut record = vec![row.to_string(), error_chain(error)];
+        for i in 0..COLUMN_COUNT as usize {
+            record.push(raw.values.get(i).cloned().flatten().unwrap_or_default());
+        }
+        self.csv.write_record(record)?;
+        Ok(())
+    }
+
+    pub fn finish(&mut self) -> Result<()> {
+        self.csv.flush()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    // A Write whose contents the test can still read after handing it to a writer.
+    #[derive(Clone, Default)]
+    struct Shared(Rc<RefCell<Vec<u8>>>);
+
+    impl Write for Shared {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.borrow_mut().write(buf)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    fn event(eventnumber: i32) -> Event {
+        Event {
+            eventnumber,
+            event_type: None,
+            server: None,
+            batch: None,
+            jobnum: None,
+            submitted: None,
+            began: read_gecs_tables::parse_datetime("2023-10-01 13:45:00").unwrap(),
+            ended: None,
+            message: None,
+            status: None,
+            priority: None,
+            fixedby: None,
+            fixcomment: None,
+            color: None,
+            bkcolor: None,
+            beingworkedon: None,
+            dateclosed: None,
+            added: None,
+        }
+    }
+
+    // As --output-file sets it up: a BufWriter that would otherwise hold the rows for a while.
+    #[test]
+    fn ndjson_rows_reach_the_file_before_the_next_one() {
+        let file = Shared::default();
+        let mut writer = writer_for(OutputFormat::Ndjson, Box::new(BufWriter::new(file.clone()))).unwrap();
+        writer.write(event(1)).unwrap();
+        let written = String::from_utf8(file.0.borrow().clone()).unwrap();
+        assert!(written.starts_with(r#"{"eventnumber":1,"#), "{}", written);
+        assert!(written.ends_with("}\n"), "{}", written);
+    }
+
+    fn format_of(name: &str) -> Option<OutputFormat> {
+        format_for_path(Path::new(name))
+    }
+
+    #[test]
+    fn known_extensions() {
+        assert_eq!(format_of("events.txt"), Some(OutputFormat::Text));
+        assert_eq!(format_of("events.json"), Some(OutputFormat::Json));
+        assert_eq!(format_of("events.csv"), Some(OutputFormat::Csv));
+        assert_eq!(format_of("events.ndjson"), Some(OutputFormat::Ndjson));
+        assert_eq!(format_of("events.jsonl"), Some(OutputFormat::Ndjson));
+    }
+
+    #[test]
+    fn extensions_ignore_case() {
+        assert_eq!(format_of("EVENTS.CSV"), Some(OutputFormat::Csv));
+        assert_eq!(format_of("events.Json"), Some(OutputFormat::Json));
+        assert_eq!(format_of("events.JSONL"), Some(OutputFormat::Ndjson));
+        assert_eq!(format_of("events.TxT"), Some(OutputFormat::Text));
+        assert_eq!(format_of("events.NDJSON"), Some(OutputFormat::Ndjson));
+    }
+
+    // Only the last extension counts, and anything without a writer here implies nothing.
+    #[test]
+    fn other_names_imply_nothing() {
+        assert_eq!(format_of("events"), None);
+        assert_eq!(format_of("events.tsv"), None);
+        assert_eq!(format_of("events.csv.gz"), None);
+        assert_eq!(format_of("events.xlsx"), None);
+        assert_eq!(format_of(".csv"), None);
+        assert_eq!(format_of("out/events.csv"), Some(OutputFormat::Csv));
+    }
+}
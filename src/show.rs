@@ -0,0 +1,251 @@
+use std::io::Write;
+
+use chrono::{Duration, Local, NaiveDateTime};
+use serde::Serialize;
+
+use crate::output::{self, OutputFormat};
+use read_gecs_tables::sql::{self, ident::Ident, Direction, Query};
+use crate::Result;
+use read_gecs_tables::{connect, create_environment, Event, EventReader, FetchMode};
+
+// How many events from the same (server, batch, jobnum) to show on each side of the event.
+const CONTEXT_EVENTS: u32 = 5;
+
+// Accepts what `show` itself prints (2023-10-01 13:45:00.003) and the other forms read_event does.
+pub fn parse_began(text: &str) -> std::result::Result<NaiveDateTime, String> {
+    read_gecs_tables::parse_datetime(text).ok_or_else(|| format!("'{}' is not a date and time like 2023-10-01 13:45:00.003", text))
+}
+
+/*
+    What `show` prints: the event itself, the times worked out from it, and its neighbours.
+    In JSON the event's own columns stay nested under "event", apart from the computed fields.
+*/
+#[derive(Serialize)]
+struct EventDetail {
+    event: Event,
+    duration_seconds: Option<i64>,
+    age_seconds: i64,
+    before: Vec<Event>,
+    after: Vec<Event>,
+}
+
+/*
+    Looks one event up by its key and prints it with its context. Everything comes from small
+    keyed queries (the event, then the events either side of it) rather than a scan of the table.
+    eventnumber alone isn't the primary key, so several rows can share it; if they do and no
+    --began was given we list the began values to choose from instead of guessing.
+*/
+pub fn run(
+    conn_str: &str,
+    table: &Ident,
+    eventnumber: i32,
+    began: Option<NaiveDateTime>,
+    fetch_mode: FetchMode,
+    format: OutputFormat,
+    mut out: Box<dyn Write>,
+) -> Result<()> {
+    if !matches!(format, OutputFormat::Text | OutputFormat::Json) {
+        return Err("show writes --output text or json".into());
+    }
+
+    let env = create_environment()?;
+    let conn = connect(&env, conn_str)?;
+    let fetch = |query: Query| -> read_gecs_tables::Result<Vec<Event>> {
+        let mut reader =
+            EventReader::with_params(&conn, &query.text, &query.params)?.fetch_mode(fetch_mode);
+        let events = reader.by_ref().collect::<read_gecs_tables::Result<_>>()?;
+        for unparsed in reader.take_unparsed() {
+            eprintln!("warning: {}; treating it as NULL", unparsed);
+        }
+        Ok(events)
+    };
+
+    let event = choose(fetch(sql::select_event(table, eventnumber, began))?, eventnumber, began)?;
+
+    let neighbours = |direction| sql::select_neighbours(table, &event, direction, CONTEXT_EVENTS);
+    let mut before = fetch(neighbours(Direction::Before))?;
+    before.reverse();
+    let after = fetch(neighbours(Direction::After))?;
+
+    // GECS stores local times without a zone, so age is measured against the local clock.
+    let detail = EventDetail {
+        duration_seconds: event.ended.map(|ended| (ended - event.began).num_seconds()),
+        age_seconds: (Local::now().naive_local() - event.began).num_seconds(),
+        event,
+        before,
+        after,
+    };
+
+    match format {
+        OutputFormat::Json => {
+            serde_json::to_writer_pretty(&mut out, &detail)?;
+            writeln!(out)?;
+        }
+        _ => write_detail(&mut out, detail)?,
+    }
+    out.flush()?;
+    Ok(())
+}
+
+/*
+    The event to show, from the rows select_event found: exactly one, or an error that says
+    what to do about none or several.
+*/
+fn choose(mut candidates: Vec<Event>, eventnumber: i32, began: Option<NaiveDateTime>) -> Result<Event> {
+    match candidates.len() {
+        0 => Err(match began {
+            Some(began) => format!("no event {} began at {}", eventnumber, began),
+            None => format!("no event with eventnumber {}", eventnumber),
+        }
+        .into()),
+        1 => Ok(candidates.remove(0)),
+        _ => {
+            let list: Vec<String> = candidates
+                .iter()
+                .map(|e| format!("  {}", e.began.format("%Y-%m-%d %H:%M:%S%.3f")))
+                .collect();
+            Err(format!(
+                "eventnumber {} matches {} events; pass --began with one of:\n{}",
+                eventnumber,
+                candidates.len(),
+                list.join("\n")
+            )
+            .into())
+        }
+    }
+}
+
+fn write_detail(out: &mut dyn Write, detail: EventDetail) -> Result<()> {
+    let heading = format!(
+        "{} / {} / {}",
+        detail.event.server.as_deref().unwrap_or("NULL"),
+        detail.event.batch.as_deref().unwrap_or("NULL"),
+        detail.event.jobnum.as_deref().unwrap_or("NULL")
+    );
+
+    output::write_text(out, detail.event)?;
+    match detail.duration_seconds {
+        Some(seconds) => writeln!(out, "Duration: {}", human_duration(seconds))?,
+        None => writeln!(out, "Duration: still open")?,
+    }
+    writeln!(out, "Age: {}", human_duration(detail.age_seconds))?;
+
+    for (label, events) in [("Before", &detail.before), ("After", &detail.after)] {
+        writeln!(out)?;
+        writeln!(out, "{} (same server / batch / jobnum: {}):", label, heading)?;
+        if events.is_empty() {
+            writeln!(out, "  (none)")?;
+        }
+        for e in events {
+            writeln!(
+                out,
+                "  {:>10}  {}  {}",
+                e.eventnumber,
+                e.began.format("%Y-%m-%d %H:%M:%S%.3f"),
+                e.message.as_deref().unwrap_or("")
+            )?;
+        }
+    }
+    Ok(())
+}
+
+// 93784 -> "1d 02h 03m 04s"; leading units that are zero are left out.
+fn human_duration(seconds: i64) -> String {
+    let d = Duration::seconds(seconds.abs());
+    let sign = if seconds < 0 { "-" } else { "" };
+    let (days, h, m, s) = (
+        d.num_days(),
+        d.num_hours() % 24,
+        d.num_minutes() % 60,
+        d.num_seconds() % 60,
+    );
+    if days > 0 {
+        format!("{}{}d {:02}h {:02}m {:02}s", sign, days, h, m, s)
+    } else if h > 0 {
+        format!("{}{}h {:02}m {:02}s", sign, h, m, s)
+    } else if m > 0 {
+        format!("{}{}m {:02}s", sign, m, s)
+    } else {
+        format!("{}{}s", sign, s)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn datetime(text: &str) -> NaiveDateTime {
+        read_gecs_tables::parse_datetime(text).unwrap()
+    }
+
+    fn event(eventnumber: i32, began: &str) -> Event {
+        Event {
+            eventnumber,
+            event_type: None,
+            server: None,
+            batch: None,
+            jobnum: None,
+            submitted: None,
+            began: datetime(began),
+            ended: None,
+            message: None,
+            status: None,
+            priority: None,
+            fixedby: None,
+            fixcomment: None,
+            color: None,
+            bkcolor: None,
+            beingworkedon: None,
+            dateclosed: None,
+            added: None,
+        }
+    }
+
+    #[test]
+    fn one_candidate_is_the_event() {
+        let chosen = choose(vec![event(7, "2023-10-01 13:45:00")], 7, None).unwrap();
+        assert_eq!(chosen.began, datetime("2023-10-01 13:45:00"));
+    }
+
+    #[test]
+    fn no_candidates_says_what_was_looked_for() {
+        let message = choose(Vec::new(), 7, None).unwrap_err().to_string();
+        assert_eq!(message, "no event with eventnumber 7");
+        let began = datetime("2023-10-01 13:45:00");
+        let message = choose(Vec::new(), 7, Some(began)).unwrap_err().to_string();
+        assert_eq!(message, "no event 7 began at 2023-10-01 13:45:00");
+    }
+
+    #[test]
+    fn several_candidates_list_the_began_values() {
+        let candidates = vec![event(7, "2023-10-01 13:45:00.003"), event(7, "2023-10-02 08:00:00")];
+        let message = choose(candidates, 7, None).unwrap_err().to_string();
+        assert_eq!(
+            message,
+            "eventnumber 7 matches 2 events; pass --began with one of:\n  2023-10-01 13:45:00.003\n  2023-10-02 08:00:00.000"
+        );
+    }
+
+    // What parse_began accepts has to include what the list above prints.
+    #[test]
+    fn listed_began_values_parse_back() {
+        assert_eq!(parse_began("2023-10-01 13:45:00.003"), Ok(datetime("2023-10-01 13:45:00.003")));
+        assert!(parse_began("yesterday").is_err());
+    }
+
+    #[test]
+    fn human_duration_units() {
+        assert_eq!(human_duration(0), "0s");
+        assert_eq!(human_duration(59), "59s");
+        assert_eq!(human_duration(61), "1m 01s");
+        assert_eq!(human_duration(3600), "1h 00m 00s");
+        assert_eq!(human_duration(93784), "1d 02h 03m 04s");
+    }
+
+    // An event that "began" in the future, from a clock that's off, still prints sensibly.
+    #[test]
+    fn human_duration_negative() {
+        assert_eq!(human_duration(-5), "-5s");
+        assert_eq!(human_duration(-93784), "-1d 02h 03m 04s");
+    }
+}
@@ -0,0 +1,539 @@
+pub mod fragment;
+pub mod ident;
+
+use chrono::NaiveDateTime;
+use fragment::Fragment;
+use ident::Ident;
+
+use crate::{Column, Event, COLUMNS};
+
+/*
+    All SQL text we send is assembled here, with Fragment: fixed text is written in this
+    module, and identifiers only enter a statement through `Ident`, which brackets and escapes
+    every part, so nothing outside this module should format a table or column name into SQL
+    itself. Values never enter the text at all: they become `?` placeholders and travel
+    alongside it as Params, which EventReader::with_params binds in order.
+    With no window, select_events is the plain `SELECT * FROM table;` the tool always ran.
+*/
+pub fn select_events(table: &Ident, window: &Window) -> Query {
+    Fragment::sql("SELECT * FROM ")
+        .ident(table)
+        .append(where_clause(window.conditions()))
+        .push(";")
+        .into_query()
+}
+
+// SQL that arrives whole, from --query, with nothing to bind.
+pub fn raw(text: &str) -> Query {
+    Query {
+        text: text.to_string(),
+        params: Vec::new(),
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum Param {
+    Int(i32),
+    Text(String),
+}
+
+/*
+    datetime values are bound as ISO 8601 text ("2023-10-01T13:45:00.003") and converted by the
+    server. The 'T' form is the one SQL Server reads the same way whatever the session's
+    DATEFORMAT or language, and three decimals carry a datetime value exactly.
+*/
+pub fn datetime_param(value: NaiveDateTime) -> Param {
+    Param::Text(value.format("%Y-%m-%dT%H:%M:%S%.3f").to_string())
+}
+
+pub struct Query {
+    pub text: String,
+    pub params: Vec<Param>,
+}
+
+// The GECSEVENTS columns the builders name, taken from COLUMNS so they can't drift from it.
+const EVENTNUMBER: Column = COLUMNS[0];
+const SERVER: Column = COLUMNS[2];
+const BATCH: Column = COLUMNS[3];
+const JOBNUM: Column = COLUMNS[4];
+const BEGAN: Column = COLUMNS[6];
+
+fn column(column: Column) -> Fragment {
+    Fragment::default().ident(&Ident::column(column))
+}
+
+// Every row with this eventnumber (optionally narrowed to one began value), oldest first.
+pub fn select_event(table: &Ident, eventnumber: i32, began: Option<NaiveDateTime>) -> Query {
+    let mut query = Fragment::sql("SELECT * FROM ")
+        .ident(table)
+        .push(" WHERE ")
+        .append(column(EVENTNUMBER))
+        .push(" = ")
+        .param(Param::Int(eventnumber));
+    if let Some(began) = began {
+        query = query
+            .push(" AND ")
+            .append(column(BEGAN))
+            .push(" = ")
+            .param(datetime_param(began));
+    }
+    query.push(" ORDER BY ").append(column(BEGAN)).push(";").into_query()
+}
+
+/*
+    --since and --until: a half-open range on `began`, since <= began < until, so consecutive
+    windows such as one day after another never return an event twice. Either end may be open.
+*/
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Window {
+    pub since: Option<NaiveDateTime>,
+    pub until: Option<NaiveDateTime>,
+}
+
+impl Window {
+    fn conditions(&self) -> Vec<Fragment> {
+        let mut conditions = Vec::new();
+        if let Some(since) = self.since {
+            conditions.push(column(BEGAN).push(" >= ").param(datetime_param(since)));
+        }
+        if let Some(until) = self.until {
+            conditions.push(column(BEGAN).push(" < ").param(datetime_param(until)));
+        }
+        conditions
+    }
+}
+
+fn where_clause(conditions: Vec<Fragment>) -> Fragment {
+    if conditions.is_empty() {
+        Fragment::default()
+    } else {
+        Fragment::sql(" WHERE ").append(Fragment::join(conditions, " AND "))
+    }
+}
+
+/*
+    SQL Server accepts at most 2100 parameters in one statement. Batches of 1000 stay well
+    clear of that and keep each statement's plan small; callers split longer lists with
+    `chunks(MAX_IN_PARAMS)`.
+*/
+pub const MAX_IN_PARAMS: usize = 1000;
+
+// The events in the window with any of these eventnumbers, in eventnumber order.
+pub fn select_by_eventnumbers(table: &Ident, eventnumbers: &[i32], window: &Window) -> Query {
+    let ids = eventnumbers
+        .iter()
+        .map(|&n| Fragment::default().param(Param::Int(n)));
+    let mut conditions = vec![column(EVENTNUMBER)
+        .push(" IN (")
+        .append(Fragment::join(ids, ", "))
+        .push(")")];
+    conditions.extend(window.conditions());
+    Fragment::sql("SELECT * FROM ")
+        .ident(table)
+        .append(where_clause(conditions))
+        .push(" ORDER BY ")
+        .append(column(EVENTNUMBER))
+        .push(", ")
+        .append(column(BEGAN))
+        .push(";")
+        .into_query()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    Before,
+    After,
+}
+
+/*
+    Up to `limit` events from the same (server, batch, jobnum) as `event`, ordered by
+    (began, eventnumber) and taken from just before or just after it. The tie-break on
+    eventnumber keeps events that began in the same tick from being skipped or repeated.
+    "Before" rows come back newest first, since TOP has to count backwards from the event;
+    the caller reverses them. A NULL key part matches other NULLs, as a person reading the
+    table would expect, which `= ?` alone would not do.
+*/
+pub fn select_neighbours(table: &Ident, event: &Event, direction: Direction, limit: u32) -> Query {
+    let key = [
+        (SERVER, event.server.as_deref()),
+        (BATCH, event.batch.as_deref()),
+        (JOBNUM, event.jobnum.as_deref()),
+    ];
+    let mut conditions: Vec<Fragment> = key
+        .into_iter()
+        .map(|(key_column, value)| match value {
+            Some(value) => column(key_column)
+                .push(" = ")
+                .param(Param::Text(value.to_string())),
+            None => column(key_column).push(" IS NULL"),
+        })
+        .collect();
+
+    let (op, order) = match direction {
+        Direction::Before => (" < ", " DESC"),
+        Direction::After => (" > ", " ASC"),
+    };
+    conditions.push(
+        Fragment::sql("(")
+            .append(column(BEGAN))
+            .push(op)
+            .param(datetime_param(event.began))
+            .push(" OR (")
+            .append(column(BEGAN))
+            .push(" = ")
+            .param(datetime_param(event.began))
+            .push(" AND ")
+            .append(column(EVENTNUMBER))
+            .push(op)
+            .param(Param::Int(event.eventnumber))
+            .push("))"),
+    );
+
+    Fragment::sql("SELECT TOP (")
+        .number(limit)
+        .push(") * FROM ")
+        .ident(table)
+        .append(where_clause(conditions))
+        .push(" ORDER BY ")
+        .append(column(BEGAN))
+        .push(order)
+        .push(", ")
+        .append(column(EVENTNUMBER))
+        .push(order)
+        .push(";")
+        .into_query()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::path::{Path, PathBuf};
+
+    fn table() -> Ident {
+        ident::parse("GECS_Testing.dbo.GECSEVENTS").unwrap()
+    }
+
+    fn placeholders(query: &Query) -> usize {
+        query.text.matches('?').count()
+    }
+
+    fn began() -> NaiveDateTime {
+        NaiveDateTime::parse_from_str("2023-10-01 13:45:00.003", "%Y-%m-%d %H:%M:%S%.3f").unwrap()
+    }
+
+    // The values bound, in order, as they would appear in the text.
+    fn bound(query: &Query) -> Vec<String> {
+        query
+            .params
+            .iter()
+            .map(|param| match param {
+                Param::Int(n) => n.to_string(),
+                Param::Text(text) => format!("'{}'", text),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn select_event_by_eventnumber() {
+        let query = select_event(&table(), 7, None);
+        assert_eq!(
+            query.text,
+            "SELECT * FROM [GECS_Testing].[dbo].[GECSEVENTS] WHERE [eventnumber] = ? ORDER BY [began];"
+        );
+        assert_eq!(bound(&query), ["7"]);
+    }
+
+    #[test]
+    fn select_event_narrowed_to_one_began() {
+        let query = select_event(&table(), 7, Some(began()));
+        assert_eq!(
+            query.text,
+            "SELECT * FROM [GECS_Testing].[dbo].[GECSEVENTS] WHERE [eventnumber] = ? AND [began] = ? ORDER BY [began];"
+        );
+        assert_eq!(bound(&query), ["7", "'2023-10-01T13:45:00.003'"]);
+    }
+
+    // Event 7, which began at began() on server srv1, job J42, with no batch.
+    fn event(server: Option<&str>, batch: Option<&str>, jobnum: Option<&str>) -> Event {
+        Event {
+            eventnumber: 7,
+            event_type: None,
+            server: server.map(str::to_string),
+            batch: batch.map(str::to_string),
+            jobnum: jobnum.map(str::to_string),
+            submitted: None,
+            began: began(),
+            ended: None,
+            message: None,
+            status: None,
+            priority: None,
+            fixedby: None,
+            fixcomment: None,
+            color: None,
+            bkcolor: None,
+            beingworkedon: None,
+            dateclosed: None,
+            added: None,
+        }
+    }
+
+    fn keyed_event() -> Event {
+        event(Some("srv1"), None, Some("J42"))
+    }
+
+    #[test]
+    fn neighbours_before_count_back_from_the_event() {
+        let query = select_neighbours(&table(), &keyed_event(), Direction::Before, 5);
+        assert_eq!(
+            query.text,
+            "SELECT TOP (5) * FROM [GECS_Testing].[dbo].[GECSEVENTS] \
+             WHERE [server] = ? AND [batch] IS NULL AND [jobnum] = ? \
+             AND ([began] < ? OR ([began] = ? AND [eventnumber] < ?)) \
+             ORDER BY [began] DESC, [eventnumber] DESC;"
+        );
+    }
+
+    #[test]
+    fn neighbours_after_count_forward_from_the_event() {
+        let query = select_neighbours(&table(), &keyed_event(), Direction::After, 5);
+        assert_eq!(
+            query.text,
+            "SELECT TOP (5) * FROM [GECS_Testing].[dbo].[GECSEVENTS] \
+             WHERE [server] = ? AND [batch] IS NULL AND [jobnum] = ? \
+             AND ([began] > ? OR ([began] = ? AND [eventnumber] > ?)) \
+             ORDER BY [began] ASC, [eventnumber] ASC;"
+        );
+    }
+
+    // A NULL key part is IS NULL with nothing bound, so the parameters still line up.
+    #[test]
+    fn neighbour_parameters_follow_the_placeholders() {
+        let query = select_neighbours(&table(), &keyed_event(), Direction::After, 5);
+        assert_eq!(placeholders(&query), query.params.len());
+        assert_eq!(
+            bound(&query),
+            [
+                "'srv1'",
+                "'J42'",
+                "'2023-10-01T13:45:00.003'",
+                "'2023-10-01T13:45:00.003'",
+                "7"
+            ]
+        );
+    }
+
+    #[test]
+    fn neighbours_with_an_all_null_key() {
+        let query = select_neighbours(&table(), &event(None, None, None), Direction::Before, 5);
+        assert!(query
+            .text
+            .contains("WHERE [server] IS NULL AND [batch] IS NULL AND [jobnum] IS NULL AND"));
+        assert_eq!(query.params.len(), 3);
+    }
+
+    #[test]
+    fn eventnumber_batches_stay_under_the_parameter_limit() {
+        let ids: Vec<i32> = (1..=2500).collect();
+        let batches: Vec<Query> = ids
+            .chunks(MAX_IN_PARAMS)
+            .map(|ids| select_by_eventnumbers(&table(), ids, &Window::default()))
+            .collect();
+        assert_eq!(batches.len(), 3);
+        for batch in &batches {
+            assert!(placeholders(batch) <= MAX_IN_PARAMS);
+            assert_eq!(placeholders(batch), batch.params.len());
+        }
+        assert_eq!(placeholders(&batches[2]), 500);
+
+        // Between them the batches bind every id once, in order.
+        let bound: Vec<i32> = batches
+            .iter()
+            .flat_map(|batch| &batch.params)
+            .map(|param| match param {
+                Param::Int(id) => *id,
+                Param::Text(text) => panic!("unexpected text parameter {}", text),
+            })
+            .collect();
+        assert_eq!(bound, ids);
+    }
+
+    #[test]
+    fn eventnumber_batches_add_the_window_after_the_ids() {
+        let since = NaiveDateTime::parse_from_str("2023-10-01 00:00:00", "%Y-%m-%d %H:%M:%S").unwrap();
+        let window = Window {
+            since: Some(since),
+            until: None,
+        };
+        let query = select_by_eventnumbers(&table(), &[7, 8], &window);
+        assert_eq!(
+            query.text,
+            "SELECT * FROM [GECS_Testing].[dbo].[GECSEVENTS] WHERE [eventnumber] IN (?, ?) AND [began] >= ? \
+             ORDER BY [eventnumber], [began];"
+        );
+        assert_eq!(query.params.len(), 3);
+        assert!(matches!(&query.params[2], Param::Text(text) if text == "2023-10-01T00:00:00.000"));
+    }
+
+    /*
+        The string literals in a Rust source file, without the ones in comments: plain strings
+        with their escapes, raw strings (r"..", r#".."#) and byte strings. Char literals such
+        as '"' are skipped over so their quote doesn't start a string, and so are lifetimes.
+    */
+    fn string_literals(source: &str) -> Vec<String> {
+        let chars: Vec<char> = source.chars().collect();
+        let is_ident = |c: char| c.is_alphanumeric() || c == '_';
+        let mut literals = Vec::new();
+        // Block comments nest in Rust.
+        let mut depth = 0;
+        let mut i = 0;
+        while i < chars.len() {
+            let c = chars[i];
+            let next = chars.get(i + 1).copied();
+            if depth > 0 {
+                match (c, next) {
+                    ('*', Some('/')) => {
+                        depth -= 1;
+                        i += 2;
+                    }
+                    ('/', Some('*')) => {
+                        depth += 1;
+                        i += 2;
+                    }
+                    _ => i += 1,
+                }
+                continue;
+            }
+            // The 'r' of br".." follows a 'b', which is not the end of an identifier.
+            let after_ident = i > 0
+                && is_ident(chars[i - 1])
+                && !(chars[i - 1] == 'b' && (i < 2 || !is_ident(chars[i - 2])));
+            match c {
+                '/' if next == Some('/') => {
+                    while i < chars.len() && chars[i] != '\n' {
+                        i += 1;
+                    }
+                }
+                '/' if next == Some('*') => {
+                    depth += 1;
+                    i += 2;
+                }
+                // No escapes in a raw string; it ends at a quote and as many #s as opened it.
+                'r' if !after_ident && matches!(next, Some('"' | '#')) => {
+                    let hashes = chars[i + 1..].iter().take_while(|&&c| c == '#').count();
+                    let open = i + 1 + hashes;
+                    if chars.get(open) != Some(&'"') {
+                        // A raw identifier, r#type.
+                        i += 1;
+                        continue;
+                    }
+                    let mut end = open + 1;
+                    while end < chars.len()
+                        && !(chars[end] == '"' && chars[end + 1..].iter().take(hashes).filter(|&&c| c == '#').count() == hashes)
+                    {
+                        end += 1;
+                    }
+                    literals.push(chars[open + 1..end.min(chars.len())].iter().collect());
+                    i = end + 1 + hashes;
+                }
+                '"' => {
+                    let mut literal = String::new();
+                    i += 1;
+                    while i < chars.len() && chars[i] != '"' {
+                        if chars[i] == '\\' {
+                            i += 1;
+                        }
+                        literal.extend(chars.get(i));
+                        i += 1;
+                    }
+                    literals.push(literal);
+                    i += 1;
+                }
+                // '"', '\'' or '\u{..}'; a lifetime such as 'a has no quote after one character.
+                '\'' if next == Some('\\') => {
+                    i += 3;
+                    while i < chars.len() && chars[i] != '\'' {
+                        i += 1;
+                    }
+                    i += 1;
+                }
+                '\'' if chars.get(i + 2) == Some(&'\'') => i += 3,
+                _ => i += 1,
+            }
+        }
+        literals
+    }
+
+    #[test]
+    fn string_literals_reads_rust_literals() {
+        let source = r##"let a = "one"; // "comment"
+            /* "block /* nested "x" */ still" */ let q = '"'; let e = '\''; let u = '\u{22}';
+            fn f<'a>(x: &'a str) -> &'a str { x }
+            let r = r#"raw "quoted" \n"#; let p = r"plain raw";
+            let s = "esc \" quote"; let b = br"bytes"; let h = r#type; let w = writer"x";"##;
+        assert_eq!(
+            string_literals(source),
+            ["one", r#"raw "quoted" \n"#, "plain raw", "esc \" quote", "bytes", "x"]
+        );
+    }
+
+    fn rust_files(dir: &Path, files: &mut Vec<PathBuf>) {
+        for entry in fs::read_dir(dir).unwrap() {
+            let path = entry.unwrap().path();
+            if path.is_dir() {
+                rust_files(&path, files);
+            } else if path.extension().is_some_and(|e| e == "rs") {
+                files.push(path);
+            }
+        }
+    }
+
+    /*
+        The query builders above write SQL only through Fragment: fixed text as &'static str,
+        names through ident() and values through param(). format! or push_str in them would be
+        a way around that.
+    */
+    #[test]
+    fn query_builders_only_use_fragment() {
+        let source = include_str!("sql.rs");
+        let builders = &source[..source.find("#[cfg(test)]").unwrap()];
+        for banned in ["format!", "push_str", "write!"] {
+            assert!(!builders.contains(banned), "src/sql.rs builds SQL with {}", banned);
+        }
+    }
+
+    #[test]
+    fn named_columns_are_the_right_ones() {
+        assert_eq!(
+            [EVENTNUMBER, SERVER, BATCH, JOBNUM, BEGAN].map(|column| column.name),
+            ["eventnumber", "server", "batch", "jobnum", "began"]
+        );
+    }
+
+    /*
+        The rule at the top of this module, checked: outside src/sql.rs and src/sql/, no string
+        literal may look like a piece of a statement or bracket an identifier by hand.
+    */
+    #[test]
+    fn sql_text_is_only_built_in_the_sql_module() {
+        let src = Path::new(env!("CARGO_MANIFEST_DIR")).join("src");
+        let mut files = Vec::new();
+        rust_files(&src, &mut files);
+        files.retain(|path| path != &src.join("sql.rs") && !path.starts_with(src.join("sql")));
+        assert!(files.iter().any(|path| path.ends_with("main.rs")));
+
+        let mut offenders = Vec::new();
+        for path in &files {
+            for literal in string_literals(&fs::read_to_string(path).unwrap()) {
+                // Keywords in capitals, as every statement here writes them, so prose doesn't match.
+                let sql_like = ["SELECT ", " FROM ", " WHERE ", " ORDER BY "]
+                    .iter()
+                    .any(|keyword| literal.contains(keyword));
+                if sql_like || literal.contains("[{}]") || literal.contains("[{") {
+                    offenders.push(format!("{}: {:?}", path.display(), literal));
+                }
+            }
+        }
+        assert!(offenders.is_empty(), "SQL built outside src/sql.rs:\n{}", offenders.join("\n"));
+    }
+}
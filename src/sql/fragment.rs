@@ -0,0 +1,116 @@
+use super::ident::Ident;
+use super::{Param, Query};
+
+/*
+    A piece of a statement and the values bound to it, built up one part at a time. Fixed SQL
+    only goes in as a &'static str, so it can only be text written in this crate; a name goes
+    in through `ident`, which brackets and escapes it, and a value through `param`, which writes
+    a `?` and keeps the value to bind. Nothing read at runtime has a way to become SQL text.
+
+        Fragment::sql("SELECT * FROM ").ident(&table).push(" WHERE ").ident(&number)
+            .push(" = ").param(Param::Int(1234567)).push(";").into_query()
+*/
+#[derive(Debug, Clone, Default)]
+pub struct Fragment {
+    text: String,
+    params: Vec<Param>,
+}
+
+impl Fragment {
+    pub fn sql(text: &'static str) -> Fragment {
+        Fragment::default().push(text)
+    }
+
+    pub fn push(mut self, text: &'static str) -> Fragment {
+        self.text.push_str(text);
+        self
+    }
+
+    pub fn ident(mut self, ident: &Ident) -> Fragment {
+        self.text.push_str(&ident.to_string());
+        self
+    }
+
+    pub fn param(mut self, param: Param) -> Fragment {
+        self.text.push('?');
+        self.params.push(param);
+        self
+    }
+
+    // A count written into the text, for the places SQL Server wants a literal, like TOP (n).
+    pub fn number(mut self, n: u32) -> Fragment {
+        self.text.push_str(&n.to_string());
+        self
+    }
+
+    // Adds `other` after this fragment; its params follow this one's, as its `?`s do.
+    pub fn append(mut self, other: Fragment) -> Fragment {
+        self.text.push_str(&other.text);
+        self.params.extend(other.params);
+        self
+    }
+
+    pub fn join(fragments: impl IntoIterator<Item = Fragment>, separator: &'static str) -> Fragment {
+        let mut joined = Fragment::default();
+        for (i, fragment) in fragments.into_iter().enumerate() {
+            if i > 0 {
+                joined = joined.push(separator);
+            }
+            joined = joined.append(fragment);
+        }
+        joined
+    }
+
+    pub fn into_query(self) -> Query {
+        Query {
+            text: self.text,
+            params: self.params,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::COLUMNS;
+
+    #[test]
+    fn parts_are_written_in_order() {
+        let table = super::super::ident::parse("dbo.[My]]Table]").unwrap();
+        let query = Fragment::sql("SELECT * FROM ")
+            .ident(&table)
+            .push(" WHERE ")
+            .ident(&Ident::column(COLUMNS[0]))
+            .push(" = ")
+            .param(Param::Int(7))
+            .push(";")
+            .into_query();
+        assert_eq!(query.text, "SELECT * FROM [dbo].[My]]Table] WHERE [eventnumber] = ?;");
+        assert!(matches!(query.params[..], [Param::Int(7)]));
+    }
+
+    #[test]
+    fn join_keeps_params_with_their_placeholders() {
+        let joined = Fragment::join(
+            [
+                Fragment::sql("a = ").param(Param::Int(1)),
+                Fragment::sql("b IS NULL"),
+                Fragment::sql("c = ").param(Param::Text("x".to_string())),
+            ],
+            " AND ",
+        )
+        .into_query();
+        assert_eq!(joined.text, "a = ? AND b IS NULL AND c = ?");
+        assert!(matches!(&joined.params[..], [Param::Int(1), Param::Text(x)] if x == "x"));
+    }
+
+    #[test]
+    fn join_of_nothing_is_empty() {
+        assert_eq!(Fragment::join(Vec::new(), ", ").into_query().text, "");
+    }
+
+    #[test]
+    fn numbers_are_written_as_text() {
+        assert_eq!(Fragment::sql("TOP (").number(5).push(")").into_query().text, "TOP (5)");
+    }
+}
@@ -0,0 +1,248 @@
+use std::fmt;
+
+use crate::{Column, ReadEcsError, Result};
+
+/*
+    SQL Server names objects with up to four parts: server.database.schema.table.
+    Each part is either a "regular" identifier (letters, digits, _, @, #, $) or a
+    "delimited" one wrapped in [brackets], which may contain anything, including dots
+    and spaces. A literal ']' inside a delimited part is written as ']]'.
+    We keep the parts unquoted in memory and only add the brackets back when rendering,
+    so every identifier that reaches the server is bracketed the same way.
+*/
+const MAX_PARTS: usize = 4;
+const MAX_PART_LEN: usize = 128; // sysname is nvarchar(128)
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Ident {
+    parts: Vec<String>,
+}
+
+/*
+    Parses a possibly multi-part identifier such as `dbo.GECSEVENTS`,
+    `[GECS_Testing].[dbo].[GECSEVENTS]` or `[My.Odd]]Name]`.
+*/
+pub fn parse(text: &str) -> Result<Ident> {
+    let mut parts = Vec::new();
+    let mut chars = text.trim().chars().peekable();
+
+    loop {
+        let part = if chars.peek() == Some(&'[') {
+            chars.next();
+            let mut part = String::new();
+            loop {
+                match chars.next() {
+                    Some(']') if chars.peek() == Some(&']') => {
+                        chars.next();
+                        part.push(']');
+                    }
+                    Some(']') => break,
+                    Some(c) if c.is_control() => {
+                        return Err(ReadEcsError::Identifier(format!("identifier '{}' contains a control character", text)))
+                    }
+                    Some(c) => part.push(c),
+                    None => return Err(ReadEcsError::Identifier(format!("identifier '{}' has an unclosed '['", text))),
+                }
+            }
+            part
+        } else {
+            let mut part = String::new();
+            while let Some(&c) = chars.peek() {
+                if c == '.' {
+                    break;
+                }
+                let valid = if part.is_empty() {
+                    c.is_alphabetic() || matches!(c, '_' | '@' | '#')
+                } else {
+                    c.is_alphanumeric() || matches!(c, '_' | '@' | '#' | '$')
+                };
+                if !valid {
+                    return Err(ReadEcsError::Identifier(format!(
+                        "identifier '{}' contains '{}'; wrap that part in [brackets]",
+                        text, c
+                    )));
+                }
+                part.push(c);
+                chars.next();
+            }
+            part
+        };
+
+        if part.is_empty() {
+            return Err(ReadEcsError::Identifier(format!("identifier '{}' has an empty part", text)));
+        }
+        if part.chars().count() > MAX_PART_LEN {
+            return Err(ReadEcsError::Identifier(format!("identifier part '{}' is longer than {} characters", part, MAX_PART_LEN)));
+        }
+        parts.push(part);
+
+        match chars.next() {
+            Some('.') => continue,
+            None => break,
+            Some(c) => return Err(ReadEcsError::Identifier(format!("unexpected '{}' after ']' in identifier '{}'", c, text))),
+        }
+    }
+
+    if parts.len() > MAX_PARTS {
+        return Err(ReadEcsError::Identifier(format!("identifier '{}' has more than {} parts", text, MAX_PARTS)));
+    }
+    Ok(Ident { parts })
+}
+
+impl fmt::Display for Ident {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, part) in self.parts.iter().enumerate() {
+            if i > 0 {
+                f.write_str(".")?;
+            }
+            write!(f, "[{}]", part.replace(']', "]]"))?;
+        }
+        Ok(())
+    }
+}
+
+impl Ident {
+    // One of the fixed GECSEVENTS columns. They are all regular identifiers, so there is
+    // nothing to validate, and taking a Column rather than text keeps other names out.
+    pub(crate) fn column(column: Column) -> Ident {
+        Ident {
+            parts: vec![column.name.to_string()],
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parts(text: &str) -> Vec<String> {
+        parse(text).unwrap().parts
+    }
+
+    #[track_caller]
+    fn rejected(text: &str) -> String {
+        match parse(text) {
+            Err(ReadEcsError::Identifier(message)) => message,
+            other => panic!("'{}' should be rejected, got {:?}", text, other),
+        }
+    }
+
+    #[test]
+    fn one_to_four_regular_parts() {
+        assert_eq!(parts("GECSEVENTS"), ["GECSEVENTS"]);
+        assert_eq!(parts("dbo.GECSEVENTS"), ["dbo", "GECSEVENTS"]);
+        assert_eq!(parts("GECS_Testing.dbo.GECSEVENTS"), ["GECS_Testing", "dbo", "GECSEVENTS"]);
+        assert_eq!(parts("srv.GECS_Testing.dbo.GECSEVENTS"), ["srv", "GECS_Testing", "dbo", "GECSEVENTS"]);
+    }
+
+    #[test]
+    fn regular_part_characters() {
+        assert_eq!(parts("#temp"), ["#temp"]);
+        assert_eq!(parts("@t"), ["@t"]);
+        assert_eq!(parts("_t$1"), ["_t$1"]);
+        assert_eq!(parts("Ereignisse_Ä"), ["Ereignisse_Ä"]);
+        rejected("1table");
+        rejected("$table");
+    }
+
+    #[test]
+    fn surrounding_whitespace_is_ignored() {
+        assert_eq!(parts("  dbo.GECSEVENTS\n"), ["dbo", "GECSEVENTS"]);
+    }
+
+    #[test]
+    fn delimited_parts_keep_dots_and_spaces() {
+        assert_eq!(parts("[GECS_Testing].[dbo].[GECSEVENTS]"), ["GECS_Testing", "dbo", "GECSEVENTS"]);
+        assert_eq!(parts("dbo.[My Table]"), ["dbo", "My Table"]);
+        assert_eq!(parts("[My.Odd].t"), ["My.Odd", "t"]);
+    }
+
+    #[test]
+    fn doubled_bracket_is_one_bracket() {
+        assert_eq!(parts("[My]]Table]"), ["My]Table"]);
+        assert_eq!(parts("[]]]"), ["]"]);
+        assert_eq!(parts("[a[b]"), ["a[b"]);
+    }
+
+    // Reserved words are only a problem unbracketed, and every part is rendered bracketed.
+    #[test]
+    fn reserved_words_are_accepted_and_bracketed() {
+        assert_eq!(parse("dbo.select").unwrap().to_string(), "[dbo].[select]");
+        assert_eq!(parse("[order].[table]").unwrap().to_string(), "[order].[table]");
+    }
+
+    #[test]
+    fn spaces_need_brackets() {
+        let message = rejected("My Table");
+        assert!(message.contains("[brackets]"), "{}", message);
+        rejected("dbo .t");
+    }
+
+    #[test]
+    fn empty_parts_are_rejected() {
+        rejected("");
+        rejected("   ");
+        rejected("a.");
+        rejected(".a");
+        rejected("a..b");
+        rejected("[]");
+    }
+
+    #[test]
+    fn text_after_closing_bracket_is_rejected() {
+        rejected("[a]b");
+        rejected("[a] .b");
+        rejected("[a]]");
+    }
+
+    #[test]
+    fn unclosed_bracket_is_rejected() {
+        let message = rejected("[GECSEVENTS");
+        assert!(message.contains("unclosed"), "{}", message);
+        rejected("dbo.[a]]");
+    }
+
+    #[test]
+    fn more_than_four_parts_are_rejected() {
+        rejected("a.b.c.d.e");
+    }
+
+    #[test]
+    fn control_characters_are_rejected() {
+        rejected("[a\nb]");
+        rejected("[a\0b]");
+        rejected("a\tb");
+    }
+
+    #[test]
+    fn parts_are_limited_to_128_characters() {
+        let longest = "a".repeat(MAX_PART_LEN);
+        assert_eq!(parts(&longest), [longest.as_str()]);
+        rejected(&format!("{}a", longest));
+        // Counted in characters, not bytes, as nvarchar does.
+        assert_eq!(parts(&format!("[{}]", "é".repeat(MAX_PART_LEN))).len(), 1);
+    }
+
+    #[test]
+    fn display_brackets_and_escapes_every_part() {
+        assert_eq!(parse("dbo.GECSEVENTS").unwrap().to_string(), "[dbo].[GECSEVENTS]");
+        assert_eq!(parse("[My]]Table]").unwrap().to_string(), "[My]]Table]");
+        assert_eq!(Ident::column(crate::COLUMNS[6]).to_string(), "[began]");
+    }
+
+    #[test]
+    fn display_parses_back_to_the_same_ident() {
+        for text in [
+            "GECSEVENTS",
+            "GECS_Testing.dbo.GECSEVENTS",
+            "[My]]Table]",
+            "[]]]",
+            "dbo.[My Table]",
+            "[My.Odd].[a b].[x]]]]y].t",
+            "[;DROP TABLE t;--]",
+        ] {
+            let ident = parse(text).unwrap();
+            assert_eq!(parse(&ident.to_string()).unwrap(), ident, "{}", text);
+        }
+    }
+}
@@ -0,0 +1,171 @@
+use chrono::{Duration, Local, NaiveDateTime};
+
+/*
+    Parses the value of --since or --until. Either an absolute time, in any of the forms
+    read_event accepts (a bare date means midnight), or a relative one: a count and a unit,
+    s, m, h, d or w, meaning that long before now. "24h" is 24 hours ago, "7d" a week ago.
+    GECS times are zone-less local times, so "now" is the local clock.
+*/
+pub fn parse(text: &str) -> Result<NaiveDateTime, String> {
+    if let Some(datetime) = read_gecs_tables::parse_datetime(text) {
+        return Ok(datetime);
+    }
+    let age = parse_duration(text).map_err(|e| match e {
+        DurationError::NotADuration => format!(
+            "'{}' is neither a date and time (2023-10-01 13:45:00) nor an age such as 24h or 7d",
+            text.trim()
+        ),
+        DurationError::Message(message) => message,
+    })?;
+    Local::now()
+        .naive_local()
+        .checked_sub_signed(age)
+        .ok_or_else(|| format!("'{}' is too far back", text))
+}
+
+pub enum DurationError {
+    // Not even shaped like a duration; the caller may try other forms or say what it expected.
+    NotADuration,
+    Message(String),
+}
+
+/*
+    A count and a unit, s, m, h, d or w: "15s", "30m", "7d". Used for the ages in --since and
+    --until, and for watch's --interval and --timeout.
+*/
+pub fn parse_duration(text: &str) -> Result<Duration, DurationError> {
+    let text = text.trim();
+    // Split before the last character, which may not be a single byte.
+    let unit_at = text.char_indices().last().map_or(0, |(i, _)| i);
+    let (count, unit) = text.split_at(unit_at);
+    // "24" would otherwise read as a count of 2 in an unknown unit "4".
+    if !text.is_empty() && text.parse::<u32>().is_ok() {
+        return Err(DurationError::Message(format!(
+            "'{}' needs a unit: s, m, h, d or w, as in {}h",
+            text, text
+        )));
+    }
+    let count: i64 = count
+        .parse::<u32>()
+        .map(i64::from)
+        .map_err(|_| DurationError::NotADuration)?;
+    let unit_seconds = match unit {
+        "s" => 1,
+        "m" => 60,
+        "h" => 60 * 60,
+        "d" => 24 * 60 * 60,
+        "w" => 7 * 24 * 60 * 60,
+        _ => {
+            return Err(DurationError::Message(format!(
+                "'{}' has an unknown unit; use s, m, h, d or w",
+                text
+            )))
+        }
+    };
+    // A u32 count of weeks is still well inside what Duration can hold.
+    Ok(Duration::seconds(count * unit_seconds))
+}
+
+// clap value_parser for flags that only take a duration.
+pub fn parse_interval(text: &str) -> Result<std::time::Duration, String> {
+    let duration = parse_duration(text).map_err(|e| match e {
+        DurationError::NotADuration => format!("'{}' is not a duration such as 15s, 5m or 2h", text),
+        DurationError::Message(message) => message,
+    })?;
+    duration
+        .to_std()
+        .ok()
+        .filter(|d| !d.is_zero())
+        .ok_or_else(|| format!("'{}' must be longer than zero", text))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // An age is relative to the clock, so check it lands between readings taken either side.
+    #[track_caller]
+    fn assert_age(text: &str, age: Duration) {
+        let before = Local::now().naive_local() - age;
+        let parsed = parse(text).unwrap();
+        let after = Local::now().naive_local() - age;
+        assert!(before <= parsed && parsed <= after, "{} gave {}", text, parsed);
+    }
+
+    #[test]
+    fn ages() {
+        assert_age("24h", Duration::hours(24));
+        assert_age("7d", Duration::days(7));
+        assert_age("2w", Duration::weeks(2));
+        assert_age("90m", Duration::minutes(90));
+        assert_age(" 30s ", Duration::seconds(30));
+    }
+
+    #[test]
+    fn absolute_times() {
+        let expected = read_gecs_tables::parse_datetime("2023-10-01 13:45:00").unwrap();
+        assert_eq!(parse("2023-10-01 13:45:00"), Ok(expected));
+    }
+
+    #[test]
+    fn a_bare_date_is_midnight() {
+        let midnight = chrono::NaiveDate::from_ymd_opt(2023, 10, 1)
+            .unwrap()
+            .and_hms_opt(0, 0, 0)
+            .unwrap();
+        assert_eq!(parse("2023-10-01"), Ok(midnight));
+    }
+
+    #[test]
+    fn durations() {
+        assert!(matches!(parse_duration("15s"), Ok(d) if d == Duration::seconds(15)));
+        assert!(matches!(parse_duration("2w"), Ok(d) if d == Duration::weeks(2)));
+    }
+
+    #[test]
+    fn an_unknown_unit_says_which_units_there_are() {
+        assert!(matches!(parse_duration("3x"), Err(DurationError::Message(m)) if m.contains("use s, m, h, d or w")));
+        let message = parse("3x").unwrap_err();
+        assert!(message.contains("unknown unit"), "{}", message);
+    }
+
+    // The unit is split off by character, so one that isn't a single byte can't cause a panic.
+    #[test]
+    fn a_multibyte_unit_is_just_unknown() {
+        assert!(matches!(parse_duration("5µ"), Err(DurationError::Message(_))));
+        assert!(matches!(parse_duration("é"), Err(DurationError::NotADuration)));
+    }
+
+    #[test]
+    fn text_that_is_no_duration_at_all() {
+        for text in ["", "   ", "h", "-3d", "1.5h", "yesterday"] {
+            assert!(matches!(parse_duration(text), Err(DurationError::NotADuration)), "{:?}", text);
+            let message = parse(text).unwrap_err();
+            assert!(message.contains("neither a date and time"), "{:?}: {}", text, message);
+        }
+    }
+
+    #[test]
+    fn a_bare_number_needs_a_unit() {
+        assert!(matches!(parse_duration("24"), Err(DurationError::Message(m)) if m == "'24' needs a unit: s, m, h, d or w, as in 24h"));
+        assert_eq!(parse_interval("15"), Err("'15' needs a unit: s, m, h, d or w, as in 15h".to_string()));
+    }
+
+    #[test]
+    fn intervals() {
+        assert_eq!(parse_interval("15s"), Ok(std::time::Duration::from_secs(15)));
+        assert_eq!(parse_interval("2h"), Ok(std::time::Duration::from_secs(2 * 60 * 60)));
+    }
+
+    #[test]
+    fn a_zero_interval_is_rejected() {
+        assert_eq!(parse_interval("0s"), Err("'0s' must be longer than zero".to_string()));
+        assert_eq!(parse_interval("0w"), Err("'0w' must be longer than zero".to_string()));
+    }
+
+    #[test]
+    fn an_interval_must_be_a_duration() {
+        let message = parse_interval("2023-10-01").unwrap_err();
+        assert!(message.contains("not a duration"), "{}", message);
+    }
+}
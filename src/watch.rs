@@ -0,0 +1,344 @@
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use chrono::{Local, NaiveDateTime};
+
+use crate::output::OutputFormat;
+use crate::Result;
+use read_gecs_tables::sql::{self, ident::Ident, Window};
+use read_gecs_tables::{connect, create_environment, Event, EventReader, FetchMode};
+
+/*
+    How a watch ended, and the exit status that reports it, so a script can tell the cases apart
+    without reading the output. 1 is already taken by "the tool itself failed".
+*/
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Outcome {
+    AllSucceeded,
+    AnyFailed,
+    TimedOut,
+}
+
+impl Outcome {
+    pub fn exit_code(self) -> i32 {
+        match self {
+            Outcome::AllSucceeded => 0,
+            Outcome::AnyFailed => 2,
+            Outcome::TimedOut => 3,
+        }
+    }
+}
+
+pub struct Options<'a> {
+    pub interval: Duration,
+    pub timeout: Option<Duration>,
+    // Status codes that mean the event failed. GECS doesn't say which codes those are, so
+    // without --failed-status an event only ever finishes by getting an ended time.
+    pub failed_statuses: &'a [u8],
+    pub fetch_mode: FetchMode,
+}
+
+// The parts of an event a person watching it cares about; a line is printed when any change.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct State {
+    status: Option<u8>,
+    ended: Option<NaiveDateTime>,
+    fixedby: Option<String>,
+}
+
+impl State {
+    fn of(event: &Event) -> State {
+        State {
+            status: event.status,
+            ended: event.ended,
+            fixedby: event.fixedby.clone(),
+        }
+    }
+
+    fn failed(&self, failed_statuses: &[u8]) -> bool {
+        self.status.is_some_and(|s| failed_statuses.contains(&s))
+    }
+
+    // Nothing more is expected to happen: the event has ended, or it has failed.
+    fn terminal(&self, failed_statuses: &[u8]) -> bool {
+        self.ended.is_some() || self.failed(failed_statuses)
+    }
+}
+
+/*
+    Polls the given events with one keyed query per interval and prints a line for each event
+    whenever its status, ended time or fixed-by changes (and once at the start). Stops when every
+    event is finished or the timeout passes, and fails as soon as one of them isn't found.
+    An eventnumber with several rows is represented by its latest began, which is the run an
+    operator is waiting on.
+*/
+pub fn run(
+    conn_str: &str,
+    table: &Ident,
+    eventnumbers: &[i32],
+    options: &Options,
+    format: OutputFormat,
+    mut out: Box<dyn Write>,
+) -> Result<Outcome> {
+    if format != OutputFormat::Text {
+        return Err("watch only writes --output text".into());
+    }
+
+    let mut ids = eventnumbers.to_vec();
+    ids.sort_unstable();
+    ids.dedup();
+    if ids.len() > sql::MAX_IN_PARAMS {
+        return Err(format!("watch can follow at most {} events at once", sql::MAX_IN_PARAMS).into());
+    }
+
+    let env = create_environment()?;
+    let conn = connect(&env, conn_str)?;
+    let query = sql::select_by_eventnumbers(table, &ids, &Window::default());
+    let deadline = options.timeout.map(|timeout| Instant::now() + timeout);
+    let mut seen: BTreeMap<i32, Option<State>> = BTreeMap::new();
+
+    loop {
+        let mut reader = EventReader::with_params(&conn, &query.text, &query.params)?
+            .fetch_mode(options.fetch_mode);
+        let events: Vec<Event> = reader.by_ref().collect::<read_gecs_tables::Result<_>>()?;
+        for unparsed in reader.take_unparsed() {
+            eprintln!("warning: {}; treating it as NULL", unparsed);
+        }
+
+        let poll = poll(&mut seen, &ids, &events, options.failed_statuses);
+        let now = Local::now().format("%H:%M:%S");
+        for line in &poll.lines {
+            writeln!(out, "{}  {}", now, line)?;
+        }
+        // Lines must show up as they happen, even through --output-file's buffer.
+        out.flush()?;
+
+        if !poll.missing.is_empty() {
+            let missing: Vec<String> = poll.missing.iter().map(|id| id.to_string()).collect();
+            return Err(format!("no event with eventnumber {}", missing.join(", ")).into());
+        }
+        if let Some(outcome) = poll.outcome {
+            return Ok(outcome);
+        }
+
+        let wait = match deadline {
+            Some(deadline) => {
+                let left = deadline.saturating_duration_since(Instant::now());
+                if left.is_zero() {
+                    let pending: Vec<String> = poll.pending.iter().map(|id| id.to_string()).collect();
+                    eprintln!("timed out; still waiting on {}", pending.join(", "));
+                    return Ok(Outcome::TimedOut);
+                }
+                left.min(options.interval)
+            }
+            None => options.interval,
+        };
+        thread::sleep(wait);
+    }
+}
+
+// What one poll found, worked out by `poll` without touching the database or the clock.
+#[derive(Debug, Default, PartialEq, Eq)]
+struct Poll {
+    // A line for each event whose state changed since the previous poll; run() adds the time.
+    lines: Vec<String>,
+    // Events the query didn't return. They can never finish, so run() gives up on them.
+    missing: Vec<i32>,
+    // Events that aren't finished yet, for the timeout message.
+    pending: Vec<i32>,
+    // Set once every event has finished.
+    outcome: Option<Outcome>,
+}
+
+/*
+    Compares one poll's rows with `seen`, the state each event had at the previous poll, and
+    updates it. `ids` is sorted, so lines come out in eventnumber order. Rows arrive ordered by
+    (eventnumber, began), so the last insert per id is its latest run.
+*/
+fn poll(seen: &mut BTreeMap<i32, Option<State>>, ids: &[i32], events: &[Event], failed_statuses: &[u8]) -> Poll {
+    let latest: BTreeMap<i32, State> = events.iter().map(|e| (e.eventnumber, State::of(e))).collect();
+    let mut poll = Poll::default();
+
+    for &id in ids {
+        let state = latest.get(&id).cloned();
+        if seen.get(&id) != Some(&state) {
+            poll.lines.push(status_line(id, state.as_ref(), failed_statuses));
+            seen.insert(id, state.clone());
+        }
+        match state {
+            None => poll.missing.push(id),
+            Some(state) if !state.terminal(failed_statuses) => poll.pending.push(id),
+            Some(_) => {}
+        }
+    }
+
+    if poll.missing.is_empty() && poll.pending.is_empty() {
+        let any_failed = latest.values().any(|state| state.failed(failed_statuses));
+        poll.outcome = Some(if any_failed {
+            Outcome::AnyFailed
+        } else {
+            Outcome::AllSucceeded
+        });
+    }
+    poll
+}
+
+//    1234567  status 3, ended 2023-10-01 13:44:58.000, fixed by jsmith  [done]
+fn status_line(eventnumber: i32, state: Option<&State>, failed_statuses: &[u8]) -> String {
+    let state = match state {
+        Some(state) => state,
+        None => return format!("{:>10}  not found", eventnumber),
+    };
+    let mut line = format!(
+        "{:>10}  status {}, ended {}, fixed by {}",
+        eventnumber,
+        state.status.map_or("NULL".to_string(), |s| s.to_string()),
+        state
+            .ended
+            .map_or("-".to_string(), |d| d.format("%Y-%m-%d %H:%M:%S%.3f").to_string()),
+        state.fixedby.as_deref().unwrap_or("-")
+    );
+    if state.failed(failed_statuses) {
+        line.push_str("  [failed]");
+    } else if state.terminal(failed_statuses) {
+        line.push_str("  [done]");
+    }
+    line
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FAILED: &[u8] = &[4, 5];
+
+    fn event(eventnumber: i32, status: u8, ended: Option<&str>, fixedby: Option<&str>) -> Event {
+        let datetime = |text: &str| read_gecs_tables::parse_datetime(text).unwrap();
+        Event {
+            eventnumber,
+            event_type: None,
+            server: None,
+            batch: None,
+            jobnum: None,
+            submitted: None,
+            began: datetime("2023-10-01 13:00:00"),
+            ended: ended.map(datetime),
+            message: None,
+            status: Some(status),
+            priority: None,
+            fixedby: fixedby.map(str::to_string),
+            fixcomment: None,
+            color: None,
+            bkcolor: None,
+            beingworkedon: None,
+            dateclosed: None,
+            added: None,
+        }
+    }
+
+    // Feeds each poll's rows through `poll` in turn, as run() would, and returns every result.
+    fn script(ids: &[i32], polls: Vec<Vec<Event>>) -> Vec<Poll> {
+        let mut seen = BTreeMap::new();
+        polls
+            .iter()
+            .map(|events| poll(&mut seen, ids, events, FAILED))
+            .collect()
+    }
+
+    #[test]
+    fn prints_every_event_on_the_first_poll() {
+        let polls = script(&[1, 2], vec![vec![event(1, 1, None, None), event(2, 2, None, None)]]);
+        assert_eq!(
+            polls[0].lines,
+            [
+                "         1  status 1, ended -, fixed by -",
+                "         2  status 2, ended -, fixed by -"
+            ]
+        );
+        assert_eq!(polls[0].pending, [1, 2]);
+        assert_eq!(polls[0].outcome, None);
+    }
+
+    #[test]
+    fn prints_only_changes() {
+        let polls = script(
+            &[1, 2],
+            vec![
+                vec![event(1, 1, None, None), event(2, 1, None, None)],
+                vec![event(1, 1, None, None), event(2, 1, None, None)],
+                vec![event(1, 1, None, Some("jsmith")), event(2, 1, None, None)],
+            ],
+        );
+        assert_eq!(polls[1].lines, Vec::<String>::new());
+        assert_eq!(polls[2].lines, ["         1  status 1, ended -, fixed by jsmith"]);
+    }
+
+    #[test]
+    fn all_ended_is_success() {
+        let polls = script(
+            &[1, 2],
+            vec![
+                vec![event(1, 1, None, None), event(2, 1, None, None)],
+                vec![event(1, 3, Some("2023-10-01 13:44:58"), None), event(2, 1, None, None)],
+                vec![
+                    event(1, 3, Some("2023-10-01 13:44:58"), None),
+                    event(2, 3, Some("2023-10-01 13:50:00"), None),
+                ],
+            ],
+        );
+        assert_eq!(polls[1].lines, ["         1  status 3, ended 2023-10-01 13:44:58.000, fixed by -  [done]"]);
+        assert_eq!(polls[1].pending, [2]);
+        assert_eq!(polls[1].outcome, None);
+        assert_eq!(polls[2].outcome, Some(Outcome::AllSucceeded));
+    }
+
+    #[test]
+    fn a_failed_status_is_terminal_and_fails_the_watch() {
+        let polls = script(
+            &[1, 2],
+            vec![
+                vec![event(1, 4, None, None), event(2, 1, None, None)],
+                vec![event(1, 4, None, None), event(2, 3, Some("2023-10-01 13:50:00"), None)],
+            ],
+        );
+        assert_eq!(polls[0].lines[0], "         1  status 4, ended -, fixed by -  [failed]");
+        assert_eq!(polls[0].pending, [2]);
+        assert_eq!(polls[1].outcome, Some(Outcome::AnyFailed));
+    }
+
+    // Without --failed-status only an ended time finishes an event.
+    #[test]
+    fn statuses_only_fail_when_listed() {
+        let mut seen = BTreeMap::new();
+        let poll = poll(&mut seen, &[1], &[event(1, 4, None, None)], &[]);
+        assert_eq!(poll.pending, [1]);
+        assert_eq!(poll.outcome, None);
+    }
+
+    #[test]
+    fn the_latest_run_of_an_eventnumber_counts() {
+        let polls = script(
+            &[1],
+            vec![vec![event(1, 3, Some("2023-10-01 12:00:00"), None), event(1, 1, None, None)]],
+        );
+        assert_eq!(polls[0].lines, ["         1  status 1, ended -, fixed by -"]);
+        assert_eq!(polls[0].outcome, None);
+    }
+
+    #[test]
+    fn a_missing_event_is_reported_and_never_finishes() {
+        let polls = script(&[1, 2], vec![vec![event(2, 3, Some("2023-10-01 13:50:00"), None)]]);
+        assert_eq!(polls[0].lines[0], "         1  not found");
+        assert_eq!(polls[0].missing, [1]);
+        assert_eq!(polls[0].outcome, None);
+    }
+
+    #[test]
+    fn exit_codes() {
+        assert_eq!(Outcome::AllSucceeded.exit_code(), 0);
+        assert_eq!(Outcome::AnyFailed.exit_code(), 2);
+        assert_eq!(Outcome::TimedOut.exit_code(), 3);
+    }
+}